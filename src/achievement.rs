@@ -0,0 +1,134 @@
+use std::{collections::BTreeSet, fs};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const ACHIEVEMENTS_PATH: &str = "./achievements.toml";
+
+/// Seconds the notification of an unlock stays on screen
+pub(crate) const TOAST_TIME: f32 = 3.0;
+
+/// A milestone that stays unlocked for good once reached in any run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Achievement {
+    Wave10,
+    Rocks1000,
+    FiveMinutes,
+    Pacifist,
+}
+
+impl Achievement {
+    pub(crate) const ALL: [Achievement; 4] = [
+        Achievement::Wave10,
+        Achievement::Rocks1000,
+        Achievement::FiveMinutes,
+        Achievement::Pacifist,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Achievement::Wave10 => "REACHED WAVE 10",
+            Achievement::Rocks1000 => "1000 ROCKS",
+            Achievement::FiveMinutes => "5 MINUTES",
+            Achievement::Pacifist => "NO SHOTS FIRED",
+        }
+    }
+
+    fn reached(&self, progress: &Progress) -> bool {
+        match self {
+            Achievement::Wave10 => progress.wave >= 10,
+            Achievement::Rocks1000 => progress.rocks_destroyed >= 1000,
+            Achievement::FiveMinutes => progress.seconds >= 5 * 60,
+            Achievement::Pacifist => progress.waves_without_firing > 0,
+        }
+    }
+}
+
+/// The counters of a run the achievements are judged by
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) struct Progress {
+    pub(crate) wave: usize,
+    pub(crate) rocks_destroyed: u32,
+    // whole seconds, so it only changes once a second
+    pub(crate) seconds: u32,
+    pub(crate) waves_without_firing: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Achievements {
+    unlocked: BTreeSet<Achievement>,
+    // what was checked last, the same progress can't unlock anything new
+    #[serde(skip)]
+    checked: Option<Progress>,
+    // only what was loaded from the file is written back, a default set never touches
+    // the disk, so headless runs and tests leave the player's unlocks alone
+    #[serde(skip)]
+    persistent: bool,
+}
+
+impl Achievements {
+    pub(crate) fn load() -> Self {
+        let mut achievements: Self = fs::read_to_string(ACHIEVEMENTS_PATH)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default();
+        achievements.persistent = true;
+        achievements
+    }
+
+    pub(crate) fn save(&self) {
+        if !self.persistent {
+            return;
+        }
+        match toml::to_string(self) {
+            Ok(content) => {
+                if let Err(err) = fs::write(ACHIEVEMENTS_PATH, content) {
+                    warn!("Unable to write achievements: {}", err);
+                }
+            }
+            Err(err) => warn!("Unable to serialize achievements: {}", err),
+        }
+    }
+
+    pub(crate) fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Unlocks what `progress` reaches for the first time and returns it. Called every
+    /// frame, but only looks through the achievements when the progress has changed.
+    pub(crate) fn check(&mut self, progress: Progress) -> Vec<Achievement> {
+        if self.checked == Some(progress) {
+            return vec![];
+        }
+        self.checked = Some(progress);
+        let reached: Vec<Achievement> = Achievement::ALL
+            .into_iter()
+            .filter(|achievement| !self.is_unlocked(*achievement) && achievement.reached(&progress))
+            .collect();
+        self.unlocked.extend(reached.iter().copied());
+        reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlocks_only_once() {
+        let mut achievements = Achievements::default();
+        let mut progress = Progress {
+            wave: 10,
+            ..Default::default()
+        };
+        assert_eq!(achievements.check(progress), vec![Achievement::Wave10]);
+        assert!(achievements.is_unlocked(Achievement::Wave10));
+
+        progress.seconds = 5 * 60;
+        assert_eq!(achievements.check(progress), vec![Achievement::FiveMinutes]);
+        progress.wave = 11;
+        assert!(achievements.check(progress).is_empty());
+    }
+}
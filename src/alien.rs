@@ -0,0 +1,223 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::WrapMode, game::playfield_delta, SCALE};
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum AlienSize {
+    Big,
+    Small,
+}
+
+impl AlienSize {
+    pub(crate) fn collision_size(&self) -> f32 {
+        match self {
+            AlienSize::Big => SCALE * 0.8,
+            AlienSize::Small => SCALE * 0.5,
+        }
+    }
+
+    pub(crate) fn direction_change_time(&self) -> f32 {
+        match self {
+            AlienSize::Big => 0.85,
+            AlienSize::Small => 0.35,
+        }
+    }
+
+    pub(crate) fn shoot_time(&self) -> f32 {
+        match self {
+            AlienSize::Big => 1.25,
+            AlienSize::Small => 0.75,
+        }
+    }
+
+    pub(crate) fn speed(&self) -> f32 {
+        match self {
+            AlienSize::Big => 3.0,
+            AlienSize::Small => 6.0,
+        }
+    }
+
+    /// A ship closer than this makes it steer away
+    pub(crate) fn flee_radius(&self) -> f32 {
+        match self {
+            AlienSize::Big => SCALE * 6.0,
+            AlienSize::Small => SCALE * 8.0,
+        }
+    }
+
+    /// How much of a new heading points away from a ship right next to it,
+    /// below 1 so some of the random wandering always remains
+    fn flee_bias(&self) -> f32 {
+        match self {
+            AlienSize::Big => 0.5,
+            AlienSize::Small => 0.8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Alien {
+    pub(crate) position: Vec2,
+    pub(crate) direction: Vec2,
+    pub(crate) size: AlienSize,
+    pub(crate) removed: bool,
+    pub(crate) last_shot: f32,
+    pub(crate) last_direction: f32,
+}
+
+impl Default for Alien {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            direction: Vec2::ZERO,
+            size: AlienSize::Small,
+            removed: false,
+            last_shot: 0.0,
+            last_direction: 0.0,
+        }
+    }
+}
+
+impl Alien {
+    pub(crate) fn new(position: Vec2, size: AlienSize) -> Self {
+        Self {
+            position,
+            size,
+            ..Default::default()
+        }
+    }
+
+    /// Radius of the circle it collides with
+    pub(crate) fn radius(&self) -> f32 {
+        self.size.collision_size()
+    }
+
+    /// Direction to shoot in at `target`, across an edge when that is the shorter way
+    pub(crate) fn aim_at(&self, target: Vec2, wrap_mode: WrapMode) -> Vec2 {
+        playfield_delta(wrap_mode, self.position, target).normalize_or_zero()
+    }
+
+    /// Seconds between two shots, shorter the more aggressive the aliens are
+    pub(crate) fn shot_interval(&self, aggression: f32) -> f32 {
+        self.size.shoot_time() / aggression
+    }
+
+    /// How far along the wind up to the next shot is, from 0 to 1, during the moment
+    /// before it fires. Follows the same interval as the shooting itself.
+    pub(crate) fn shot_wind_up(&self, now: f32, aggression: f32) -> Option<f32> {
+        const WIND_UP: f32 = 0.3;
+        let interval = self.shot_interval(aggression);
+        // shrinks with a quick interval, so it never runs into the last shot
+        let wind_up = WIND_UP.min(interval * 0.4);
+        let until_shot = interval - (now - self.last_shot);
+        (until_shot < wind_up).then(|| (1.0 - until_shot / wind_up).clamp(0.0, 1.0))
+    }
+
+    /// The next heading, the random `wander` bent away from a ship at `to_ship` the
+    /// closer it is. A far away ship or none leaves the wandering as it is.
+    pub(crate) fn flee_direction(&self, wander: Vec2, to_ship: Option<Vec2>) -> Vec2 {
+        let Some(to_ship) = to_ship else {
+            return wander;
+        };
+        let radius = self.size.flee_radius();
+        let distance = to_ship.length();
+        if distance >= radius {
+            return wander;
+        }
+        let bias = self.size.flee_bias() * (1.0 - distance / radius);
+        let away = -to_ship.normalize_or_zero();
+        (wander * (1.0 - bias) + away * bias)
+            .try_normalize()
+            .unwrap_or(wander)
+    }
+
+    /// Where the rest of the flock at `others` pulls it: apart from the ones too close,
+    /// towards where they are on the whole and towards the ship at `to_ship`.
+    /// Nothing for an alien on its own, that one only wanders.
+    pub(crate) fn flock_steering(
+        &self,
+        others: &[Vec2],
+        to_ship: Option<Vec2>,
+        wrap_mode: WrapMode,
+    ) -> Vec2 {
+        // closer than this they get in each other's way
+        const SEPARATION: f32 = SCALE * 4.0;
+        const COHESION_WEIGHT: f32 = 0.4;
+        const TARGET_WEIGHT: f32 = 0.6;
+
+        if others.is_empty() {
+            return Vec2::ZERO;
+        }
+        let mut separation = Vec2::ZERO;
+        let mut center = Vec2::ZERO;
+        for other in others {
+            let delta = playfield_delta(wrap_mode, self.position, *other);
+            center += delta;
+            let distance = delta.length();
+            if distance < SEPARATION {
+                // two on top of each other have no way apart, the wandering splits them
+                separation -= delta.normalize_or_zero() * (1.0 - distance / SEPARATION);
+            }
+        }
+        let cohesion = (center / others.len() as f32).normalize_or_zero();
+        let target = to_ship.map_or(Vec2::ZERO, Vec2::normalize_or_zero);
+        separation + cohesion * COHESION_WEIGHT + target * TARGET_WEIGHT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SIZE;
+
+    #[test]
+    fn aims_across_the_edge_at_a_ship_just_beyond_it() {
+        let alien = Alien::new(Vec2::new(SIZE.x - SCALE, SIZE.y * 0.5), AlienSize::Big);
+        let ship = Vec2::new(SCALE, SIZE.y * 0.5);
+        assert_eq!(alien.aim_at(ship, WrapMode::Wrap), Vec2::X);
+        // without wrapping there is only the long way
+        assert_eq!(alien.aim_at(ship, WrapMode::Bounce), -Vec2::X);
+    }
+
+    #[test]
+    fn keeps_its_distance_in_a_flock_but_stays_on_its_own() {
+        let alien = Alien::new(Vec2::new(SIZE.x * 0.5, SIZE.y * 0.5), AlienSize::Big);
+        let ship = Some(Vec2::new(0.0, SCALE * 10.0));
+        assert_eq!(alien.flock_steering(&[], ship, WrapMode::Wrap), Vec2::ZERO);
+
+        let crowding = alien.position + Vec2::new(SCALE, 0.0);
+        let steering = alien.flock_steering(&[crowding], None, WrapMode::Wrap);
+        assert!(steering.x < 0.0);
+
+        let distant = alien.position + Vec2::new(SCALE * 10.0, 0.0);
+        let steering = alien.flock_steering(&[distant], ship, WrapMode::Wrap);
+        assert!(steering.x > 0.0);
+        assert!(steering.y > 0.0);
+    }
+
+    #[test]
+    fn winds_up_just_before_the_shot_at_any_aggression() {
+        let alien = Alien::new(Vec2::ZERO, AlienSize::Small);
+        for aggression in [1.0, 2.0] {
+            let interval = alien.shot_interval(aggression);
+            assert_eq!(alien.shot_wind_up(interval * 0.5, aggression), None);
+            let almost = alien.shot_wind_up(interval - 0.01, aggression).unwrap();
+            assert!(almost > 0.9);
+        }
+    }
+
+    #[test]
+    fn steers_away_from_a_close_ship_but_keeps_wandering() {
+        let alien = Alien::new(Vec2::ZERO, AlienSize::Big);
+        let wander = Vec2::Y;
+        let far = Vec2::new(SCALE * 20.0, 0.0);
+        assert_eq!(alien.flee_direction(wander, Some(far)), wander);
+        assert_eq!(alien.flee_direction(wander, None), wander);
+
+        let close = Vec2::new(SCALE, 0.0);
+        let direction = alien.flee_direction(wander, Some(close));
+        assert!(direction.x < 0.0);
+        assert!(direction.y > 0.0);
+    }
+}
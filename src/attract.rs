@@ -0,0 +1,51 @@
+use macroquad::prelude::*;
+
+use crate::{
+    game::{playfield_delta, wrap_angle, State},
+    input::{FrameInput, InputProvider, Step},
+    SCALE,
+};
+
+/// Plays the demo shown after idling in the menu
+pub(crate) struct AttractInput;
+
+impl InputProvider for AttractInput {
+    fn next_step(&mut self, state: &State) -> Option<Step> {
+        Some(Step {
+            delta: get_frame_time(),
+            inputs: vec![autopilot(state)],
+        })
+    }
+}
+
+/// Turns the first ship towards the nearest rock and fires once it roughly faces it,
+/// only thrusting to close in on rocks that are far away
+fn autopilot(state: &State) -> FrameInput {
+    // radians the ship may be off and still fire
+    const AIM_TOLERANCE: f32 = 0.15;
+    // radians that count as facing the target, avoids jittering left and right
+    const TURN_DEAD_ZONE: f32 = 0.05;
+    const APPROACH_DISTANCE: f32 = SCALE * 10.0;
+
+    let ship = state.ship();
+    let wrap_mode = state.config.wrap_mode;
+    let nearest = state
+        .rocks
+        .iter()
+        .map(|rock| playfield_delta(wrap_mode, ship.position, rock.position))
+        .min_by(|a, b| a.length().total_cmp(&b.length()));
+    let Some(to_rock) = nearest else {
+        return FrameInput::default();
+    };
+
+    // the ship faces its rotation plus PI * 0.5, see `update_player`
+    let target = to_rock.y.atan2(to_rock.x) - std::f32::consts::PI * 0.5;
+    let difference = wrap_angle(target - ship.rotation);
+    FrameInput {
+        left: difference > TURN_DEAD_ZONE,
+        right: difference < -TURN_DEAD_ZONE,
+        thrust: difference.abs() < AIM_TOLERANCE && to_rock.length() > APPROACH_DISTANCE,
+        fire: difference.abs() < AIM_TOLERANCE,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,535 @@
+use std::fs;
+
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::SIZE;
+
+const CONFIG_PATH: &str = "./config.toml";
+pub(crate) const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How debris and sparks are colored
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ParticleColors {
+    // everything in the line color, like the original vector monitors
+    Classic,
+    // explosions in reds and oranges
+    Warm,
+}
+
+/// What happens at the edges of the playfield
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WrapMode {
+    // leaving on one side enters on the other
+    Wrap,
+    // a walled arena, everything bounces off its edges
+    Bounce,
+}
+
+/// What a projectile does when it reaches a wall of a `WrapMode::Bounce` arena
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WallHit {
+    Bounce,
+    Die,
+}
+
+// Keys that can be bound, written in the config the way they are named here
+const BINDABLE_KEYS: [KeyCode; 58] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Escape,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::LeftBracket,
+    KeyCode::RightBracket,
+];
+
+/// A `KeyCode` read from its name, e.g. "Space" or "LeftShift"
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct Key(pub(crate) KeyCode);
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        BINDABLE_KEYS
+            .iter()
+            .find(|key| format!("{:?}", key).eq_ignore_ascii_case(&name))
+            .map(|&key| Key(key))
+            .ok_or_else(|| format!("unknown key '{}'", name))
+    }
+}
+
+/// Keyboard controls of the first ship. The mouse and gamepads keep working alongside.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+pub(crate) struct KeyBindings {
+    pub(crate) left: Key,
+    pub(crate) right: Key,
+    pub(crate) thrust: Key,
+    // thrusts downwards with the twin-stick controls, unused with the others
+    pub(crate) reverse: Key,
+    pub(crate) fire: Key,
+    pub(crate) dash: Key,
+    pub(crate) bomb: Key,
+    pub(crate) pause: Key,
+    // starts the run over, pressed twice while it is still going
+    pub(crate) restart: Key,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: Key(KeyCode::A),
+            right: Key(KeyCode::D),
+            thrust: Key(KeyCode::W),
+            reverse: Key(KeyCode::S),
+            fire: Key(KeyCode::Space),
+            dash: Key(KeyCode::LeftControl),
+            bomb: Key(KeyCode::B),
+            pause: Key(KeyCode::P),
+            restart: Key(KeyCode::R),
+        }
+    }
+}
+
+/// Keys doing a fixed thing during a run, out of reach of the bindings: the pause and its
+/// options, the control scheme, the audio, debug and save hotkeys, the sandbox spawns and
+/// leaving the shop
+const FIXED_KEYS: [KeyCode; 30] = [
+    KeyCode::Escape,
+    KeyCode::O,
+    KeyCode::Q,
+    KeyCode::C,
+    KeyCode::M,
+    KeyCode::Equal,
+    KeyCode::Minus,
+    KeyCode::KpAdd,
+    KeyCode::KpSubtract,
+    KeyCode::G,
+    KeyCode::I,
+    KeyCode::T,
+    KeyCode::N,
+    KeyCode::Period,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F11,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Backspace,
+    KeyCode::Tab,
+];
+
+/// The second co-op ship's keys, only taken while it plays
+const COOP_KEYS: [KeyCode; 7] = [
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::Enter,
+];
+
+impl KeyBindings {
+    /// A key bound to more than one action or to one of the fixed keys, if any
+    fn conflict(&self, coop: bool) -> Option<KeyCode> {
+        let keys = [
+            self.left,
+            self.right,
+            self.thrust,
+            self.reverse,
+            self.fire,
+            self.dash,
+            self.bomb,
+            self.pause,
+            self.restart,
+        ];
+        let fixed = |key: &Key| FIXED_KEYS.contains(&key.0) || (coop && COOP_KEYS.contains(&key.0));
+        keys.iter()
+            .enumerate()
+            .find(|(index, key)| keys[index + 1..].contains(key) || fixed(key))
+            .map(|(_, key)| key.0)
+    }
+}
+
+/// Hand edited options read once at startup, unlike `Settings` which the game writes itself
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct Config {
+    // shown in the window title and on the menu
+    pub(crate) title: String,
+    // the build version next to the title, helps with bug reports
+    pub(crate) show_version: bool,
+    pub(crate) particle_colors: ParticleColors,
+    // the outlines of ships, rocks, aliens and power-ups bloom like on a vector monitor.
+    // The HUD and particles stay crisp so they are easy to read.
+    pub(crate) phosphor_glow: bool,
+    // the edges of the playfield darken a little, framing it
+    pub(crate) vignette: bool,
+    // a second ship on the same keyboard
+    pub(crate) coop: bool,
+    // in co-op both ships draw from one pool of lives instead of each having their own
+    pub(crate) shared_lives: bool,
+    // whether the ships' shots can destroy each other
+    pub(crate) friendly_fire: bool,
+    // whether a ship's own shot can destroy it, say after going around a wrapping playfield
+    pub(crate) self_hit: bool,
+    pub(crate) wrap_mode: WrapMode,
+    pub(crate) projectiles_at_walls: WallHit,
+    pub(crate) key_bindings: KeyBindings,
+    // waits for the display's refresh before showing a frame
+    pub(crate) vsync: bool,
+    // frames per second the main loop is held to, 0 runs as fast as it can
+    pub(crate) target_fps: u32,
+    // a CSV file the events of every run are appended to, for looking into the balance.
+    // `--log <path>` on the command line takes precedence.
+    pub(crate) run_log: Option<String>,
+    // window pixels per logical pixel of the playfield, also the size the window opens
+    // with. The playfield still shrinks to fit a smaller window. 0 always fills the window.
+    pub(crate) render_scale: f32,
+    // particles on screen at once, older and smaller ones make room beyond it. 0 for no limit.
+    pub(crate) max_particles: usize,
+    // rocks on the field at once, fragments beyond it are never spawned and waves are
+    // generated smaller. A safeguard for slow machines, not meant to be reached in play.
+    pub(crate) max_rocks: usize,
+    // later waves may bring a gravity well
+    pub(crate) gravity_wells: bool,
+    // the rocks of a new wave keep their distance from the ships
+    pub(crate) spawn_away_from_ship: bool,
+    // pixels new rocks and aliens keep from the edges, so nothing appears half off-screen.
+    // Only spawns, things still wrap across the edges as before.
+    pub(crate) spawn_margin: f32,
+    // share of its velocity the ship loses every frame, low values drift on for long.
+    // The precision flight mode brakes harder once the thrust lets go.
+    pub(crate) drag: f32,
+    // how hard a shot pushes the ship back, 0 turns it off
+    pub(crate) recoil: f32,
+    // turns with the keys or a stick take a moment to build up and wind down,
+    // off turns at full speed right away
+    pub(crate) turn_acceleration: bool,
+    // a faint arrow from the ship to the closest rock or alien, for new players
+    pub(crate) assist_arrow: bool,
+    // a faint line along the path a shot would take, for new players
+    pub(crate) aim_line: bool,
+    // aliens steer away from a ship that gets close instead of only drifting at random
+    pub(crate) alien_flee: bool,
+    // with several aliens around, how strongly they keep apart and close in on the ship
+    // together, from 0 for every alien on its own to 1
+    pub(crate) alien_flocking: f32,
+    // aliens on screen at once, the score doesn't bring any more while they are all
+    // around. Also bounds how large a flock can grow.
+    pub(crate) max_aliens: usize,
+    // a dot grows on an alien in the moment before it shoots
+    pub(crate) alien_telegraph: bool,
+    // the world slows down and turns red while a ship on its last life is close to something
+    pub(crate) last_stand: bool,
+    // the number of rocks follows how well the last waves went, off for comparable scores
+    pub(crate) adaptive_difficulty: bool,
+    // pixels per frame of a regular shot of the ships, together with the lifetime its range
+    pub(crate) shot_speed: f32,
+    // seconds a regular shot of the ships lives, special weapons stretch it
+    pub(crate) shot_lifetime: f32,
+    // shots add the ship's velocity to their own like real physics. Their range then
+    // varies with the ship's speed, further forwards and shorter backwards.
+    pub(crate) inherit_momentum: bool,
+    // holding fire keeps shooting as fast as the cooldown and the shot cap allow,
+    // in place of charging a piercing shot. Off takes a press for every shot.
+    pub(crate) auto_fire: bool,
+    // destroyed rocks sometimes leave ore behind, spent on upgrades for the run in a shop
+    // between waves
+    pub(crate) shop: bool,
+    // seconds a regular alien shot lives, the homing missiles keep their own
+    pub(crate) alien_shot_lifetime: f32,
+    // Accessibility: the size of what the ship collides with compared to its outline,
+    // below 1 forgives near misses. Left alone by the difficulty settings.
+    pub(crate) ship_hitbox_scale: f32,
+    // seconds a lost ship takes to come back
+    pub(crate) respawn_delay: f32,
+    // fire brings the ship back early once the explosion has mostly faded
+    pub(crate) skip_respawn_delay: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            title: String::from("BIG SPACE ROCKS"),
+            show_version: true,
+            particle_colors: ParticleColors::Warm,
+            phosphor_glow: false,
+            vignette: false,
+            coop: false,
+            shared_lives: true,
+            friendly_fire: false,
+            self_hit: false,
+            wrap_mode: WrapMode::Wrap,
+            projectiles_at_walls: WallHit::Die,
+            key_bindings: KeyBindings::default(),
+            vsync: true,
+            target_fps: 0,
+            run_log: None,
+            render_scale: 0.0,
+            max_particles: 4000,
+            max_rocks: 400,
+            gravity_wells: true,
+            spawn_away_from_ship: true,
+            spawn_margin: 0.0,
+            drag: 0.015,
+            recoil: 0.5,
+            turn_acceleration: true,
+            assist_arrow: false,
+            aim_line: false,
+            alien_flee: true,
+            alien_flocking: 0.3,
+            max_aliens: 4,
+            alien_telegraph: true,
+            last_stand: true,
+            adaptive_difficulty: false,
+            shot_speed: 10.0,
+            shot_lifetime: 1.0,
+            inherit_momentum: false,
+            auto_fire: false,
+            shop: false,
+            alien_shot_lifetime: 2.0,
+            ship_hitbox_scale: 1.0,
+            respawn_delay: 3.0,
+            skip_respawn_delay: true,
+        }
+    }
+}
+
+impl Config {
+    /// The title with the version appended, if it should be shown
+    pub(crate) fn window_title(&self) -> String {
+        if self.show_version {
+            format!("{} {}", self.title, VERSION)
+        } else {
+            self.title.clone()
+        }
+    }
+
+    /// What a daily challenge is played with: the defaults, so everyone plays the same
+    /// game, keeping only what changes how it looks and runs on this machine
+    pub(crate) fn for_daily(&self) -> Self {
+        Self {
+            title: self.title.clone(),
+            show_version: self.show_version,
+            particle_colors: self.particle_colors,
+            phosphor_glow: self.phosphor_glow,
+            vignette: self.vignette,
+            key_bindings: self.key_bindings,
+            vsync: self.vsync,
+            target_fps: self.target_fps,
+            run_log: self.run_log.clone(),
+            render_scale: self.render_scale,
+            max_particles: self.max_particles,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn load() -> Self {
+        let Ok(content) = fs::read_to_string(CONFIG_PATH) else {
+            return Self::default();
+        };
+        let mut config: Self = match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("Ignoring invalid {}: {}", CONFIG_PATH, err);
+                return Self::default();
+            }
+        };
+        // one key doing two things would leave one of them unreachable
+        if let Some(key) = config.key_bindings.conflict(config.coop) {
+            warn!(
+                "{:?} is bound to more than one action, using the default keys",
+                key
+            );
+            config.key_bindings = KeyBindings::default();
+        }
+        if config.recoil < 0.0 {
+            warn!("A negative recoil would pull the ship forward, turning it off instead");
+            config.recoil = 0.0;
+        }
+        // shots that never leave the ship would make the game unwinnable
+        let defaults = Self::default();
+        if config.shot_speed <= 0.0 {
+            warn!("The shot speed has to be positive, using the default");
+            config.shot_speed = defaults.shot_speed;
+        }
+        if config.shot_lifetime <= 0.0 {
+            warn!("The shot lifetime has to be positive, using the default");
+            config.shot_lifetime = defaults.shot_lifetime;
+        }
+        if config.alien_shot_lifetime <= 0.0 {
+            warn!("The alien shot lifetime has to be positive, using the default");
+            config.alien_shot_lifetime = defaults.alien_shot_lifetime;
+        }
+        // much smaller and nothing ever hits, much larger and the ship can't move
+        const HITBOX_SCALES: std::ops::RangeInclusive<f32> = 0.25..=2.0;
+        if !HITBOX_SCALES.contains(&config.ship_hitbox_scale) {
+            warn!(
+                "A ship hitbox scale of {} is out of range, keeping it between {} and {}",
+                config.ship_hitbox_scale,
+                HITBOX_SCALES.start(),
+                HITBOX_SCALES.end()
+            );
+            config.ship_hitbox_scale = config
+                .ship_hitbox_scale
+                .clamp(*HITBOX_SCALES.start(), *HITBOX_SCALES.end());
+        }
+        // a handful of rocks would make every wave trivial
+        const MIN_ROCKS: usize = 64;
+        if config.max_rocks < MIN_ROCKS {
+            warn!(
+                "A rock cap of {} is too low to play with, raising it to {}",
+                config.max_rocks, MIN_ROCKS
+            );
+            config.max_rocks = MIN_ROCKS;
+        }
+        if !(0.0..=1.0).contains(&config.alien_flocking) {
+            warn!(
+                "An alien flocking strength of {} is out of range, keeping it between 0 and 1",
+                config.alien_flocking
+            );
+            config.alien_flocking = config.alien_flocking.clamp(0.0, 1.0);
+        }
+        // at most a third of the shorter side, leaving the middle free to spawn in
+        let max_margin = SIZE.min_element() / 3.0;
+        if !(0.0..=max_margin).contains(&config.spawn_margin) {
+            warn!(
+                "A spawn margin of {} is out of range, keeping it between 0 and {}",
+                config.spawn_margin, max_margin
+            );
+            config.spawn_margin = config.spawn_margin.clamp(0.0, max_margin);
+        }
+        const RENDER_SCALES: std::ops::RangeInclusive<f32> = 0.25..=4.0;
+        if config.render_scale != 0.0 && !RENDER_SCALES.contains(&config.render_scale) {
+            warn!(
+                "A render scale of {} is out of range, keeping it between {} and {}",
+                config.render_scale,
+                RENDER_SCALES.start(),
+                RENDER_SCALES.end()
+            );
+            config.render_scale = config
+                .render_scale
+                .clamp(*RENDER_SCALES.start(), *RENDER_SCALES.end());
+        }
+        // from not slowing down at all to stopping within a few frames
+        if !(0.0..=0.5).contains(&config.drag) {
+            warn!(
+                "A drag of {} is out of range, keeping it between 0 and 0.5",
+                config.drag
+            );
+            config.drag = config.drag.clamp(0.0, 0.5);
+        }
+        if config.respawn_delay < 0.0 {
+            warn!("A negative respawn delay doesn't make sense, respawning right away");
+            config.respawn_delay = 0.0;
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_key_names_ignoring_case() {
+        assert!(Key::try_from(String::from("Space")) == Ok(Key(KeyCode::Space)));
+        assert!(Key::try_from(String::from("leftshift")) == Ok(Key(KeyCode::LeftShift)));
+        assert!(Key::try_from(String::from("Key1")) == Ok(Key(KeyCode::Key1)));
+        assert!(Key::try_from(String::from("Hyperdrive")).is_err());
+    }
+
+    #[test]
+    fn finds_keys_bound_twice() {
+        assert!(KeyBindings::default().conflict(true).is_none());
+        let bindings = KeyBindings {
+            pause: Key(KeyCode::W),
+            ..Default::default()
+        };
+        assert!(bindings.conflict(false) == Some(KeyCode::W));
+    }
+
+    #[test]
+    fn finds_keys_taken_by_a_fixed_action() {
+        let bindings = KeyBindings {
+            bomb: Key(KeyCode::M),
+            ..Default::default()
+        };
+        assert!(bindings.conflict(false) == Some(KeyCode::M));
+
+        // the arrows are free unless the second ship needs them
+        let bindings = KeyBindings {
+            thrust: Key(KeyCode::Up),
+            ..Default::default()
+        };
+        assert!(bindings.conflict(false).is_none());
+        assert!(bindings.conflict(true) == Some(KeyCode::Up));
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::SystemTime;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Days since 1970-01-01 in UTC, everyone playing on the same day gets the same one
+pub(crate) fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("We should be after 1970")
+        .as_secs()
+        / SECONDS_PER_DAY
+}
+
+/// Seed of the challenge on `day`. Mixed so neighbouring days don't start alike.
+pub(crate) fn seed(day: u64) -> u64 {
+    // the finalizer of SplitMix64
+    let mut z = day.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// `day` written as YYYY-MM-DD, also the key of its high score
+pub(crate) fn date(day: u64) -> String {
+    // Howard Hinnant's days_from_civil in reverse, which saves pulling in a date crate
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_days_as_dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(20_742), "2026-10-16");
+    }
+
+    #[test]
+    fn every_day_has_its_own_seed() {
+        assert_eq!(seed(20_742), seed(20_742));
+        assert_ne!(seed(20_742), seed(20_743));
+    }
+}
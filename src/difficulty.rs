@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+/// A slowly moving guess at how well the player is doing, scaling the rocks of the next
+/// field. 1 is the regular difficulty.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Skill {
+    pub(crate) level: f32,
+    // a ship was lost since the last wave was cleared
+    lost_life: bool,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            lost_life: false,
+        }
+    }
+}
+
+impl Skill {
+    const MIN: f32 = 0.7;
+    const MAX: f32 = 1.3;
+    // share of the way towards what a single wave showed, keeps it from swinging around
+    const SMOOTHING: f32 = 0.25;
+    const LIFE_LOST_PENALTY: f32 = 0.05;
+    // seconds to clear a wave counted as fast and as slow
+    const FAST_CLEAR: f32 = 30.0;
+    const SLOW_CLEAR: f32 = 120.0;
+
+    pub(crate) fn life_lost(&mut self) {
+        self.lost_life = true;
+        self.level = (self.level - Self::LIFE_LOST_PENALTY).max(Self::MIN);
+    }
+
+    /// Moves the estimate a bit towards how the wave went: fast and without a loss up,
+    /// slow or with a loss down
+    pub(crate) fn wave_cleared(&mut self, seconds: f32) {
+        let pace =
+            ((Self::SLOW_CLEAR - seconds) / (Self::SLOW_CLEAR - Self::FAST_CLEAR)).clamp(0.0, 1.0);
+        let target = if self.lost_life {
+            Self::MIN
+        } else {
+            Self::MIN + (Self::MAX - Self::MIN) * pace
+        };
+        self.level =
+            (self.level + (target - self.level) * Self::SMOOTHING).clamp(Self::MIN, Self::MAX);
+        self.lost_life = false;
+    }
+
+    /// A new run starts with a clean wave, the estimate itself carries over
+    pub(crate) fn new_run(&mut self) {
+        self.lost_life = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rises_slowly_for_fast_waves_and_stays_in_bounds() {
+        let mut skill = Skill::default();
+        skill.wave_cleared(20.0);
+        assert!(skill.level > 1.0 && skill.level < 1.1);
+        for _ in 0..50 {
+            skill.wave_cleared(20.0);
+        }
+        assert!(skill.level <= Skill::MAX);
+    }
+
+    #[test]
+    fn alternating_waves_do_not_swing_it_around() {
+        let mut skill = Skill::default();
+        let mut previous = skill.level;
+        for wave in 0..20 {
+            if wave % 2 == 1 {
+                skill.life_lost();
+            }
+            skill.wave_cleared(25.0);
+            assert!((skill.level - previous).abs() < 0.2);
+            previous = skill.level;
+        }
+    }
+}
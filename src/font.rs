@@ -0,0 +1,195 @@
+use macroquad::math::Vec2;
+
+// corners and middles of a glyph's unit box, y grows downwards like on screen
+const L: f32 = -0.5;
+const R: f32 = 0.5;
+const T: f32 = -0.5;
+const B: f32 = 0.5;
+const M: f32 = 0.0;
+
+const fn p(x: f32, y: f32) -> Vec2 {
+    Vec2::new(x, y)
+}
+
+/// A glyph is drawn as a few open polylines inside a unit box around its center
+pub(crate) type Glyph = &'static [&'static [Vec2]];
+
+const DIGITS: [Glyph; 10] = [
+    &[&[p(L, B), p(R, B), p(R, T), p(L, T), p(L, B)]],
+    &[&[p(M, B), p(M, T)]],
+    &[&[p(L, T), p(R, T), p(R, M), p(L, M), p(L, B), p(R, B)]],
+    &[&[
+        p(L, T),
+        p(R, T),
+        p(R, M),
+        p(L, M),
+        p(R, M),
+        p(R, B),
+        p(L, B),
+    ]],
+    &[&[p(L, T), p(L, M), p(R, M), p(R, T), p(R, B)]],
+    &[&[p(R, T), p(L, T), p(L, M), p(R, M), p(R, B), p(L, B)]],
+    &[&[p(L, T), p(L, B), p(R, B), p(R, M), p(L, M)]],
+    &[&[p(L, T), p(R, T), p(R, B)]],
+    &[&[
+        p(L, B),
+        p(R, B),
+        p(R, T),
+        p(L, T),
+        p(L, M),
+        p(R, M),
+        p(L, M),
+        p(L, B),
+    ]],
+    &[&[p(R, B), p(R, T), p(L, T), p(L, M), p(R, M)]],
+];
+
+const LETTERS: [Glyph; 26] = [
+    // A
+    &[
+        &[p(L, B), p(L, M), p(M, T), p(R, M), p(R, B)],
+        &[p(L, M), p(R, M)],
+    ],
+    // B
+    &[&[
+        p(L, B),
+        p(L, T),
+        p(0.2, T),
+        p(R, -0.25),
+        p(0.2, M),
+        p(R, 0.25),
+        p(0.2, B),
+        p(L, B),
+    ]],
+    // C
+    &[&[p(R, T), p(L, T), p(L, B), p(R, B)]],
+    // D
+    &[&[
+        p(L, T),
+        p(0.1, T),
+        p(R, -0.2),
+        p(R, 0.2),
+        p(0.1, B),
+        p(L, B),
+        p(L, T),
+    ]],
+    // E
+    &[
+        &[p(R, T), p(L, T), p(L, B), p(R, B)],
+        &[p(L, M), p(0.25, M)],
+    ],
+    // F
+    &[&[p(R, T), p(L, T), p(L, B)], &[p(L, M), p(0.25, M)]],
+    // G
+    &[&[
+        p(R, -0.25),
+        p(R, T),
+        p(L, T),
+        p(L, B),
+        p(R, B),
+        p(R, M),
+        p(M, M),
+    ]],
+    // H
+    &[
+        &[p(L, T), p(L, B)],
+        &[p(R, T), p(R, B)],
+        &[p(L, M), p(R, M)],
+    ],
+    // I
+    &[
+        &[p(L, T), p(R, T)],
+        &[p(M, T), p(M, B)],
+        &[p(L, B), p(R, B)],
+    ],
+    // J
+    &[&[p(R, T), p(R, B), p(M, B), p(L, 0.2)]],
+    // K
+    &[&[p(L, T), p(L, B)], &[p(R, T), p(L, M), p(R, B)]],
+    // L
+    &[&[p(L, T), p(L, B), p(R, B)]],
+    // M
+    &[&[p(L, B), p(L, T), p(M, M), p(R, T), p(R, B)]],
+    // N
+    &[&[p(L, B), p(L, T), p(R, B), p(R, T)]],
+    // O
+    &[&[p(L, T), p(R, T), p(R, B), p(L, B), p(L, T)]],
+    // P
+    &[&[p(L, B), p(L, T), p(R, T), p(R, M), p(L, M)]],
+    // Q
+    &[
+        &[p(L, T), p(R, T), p(R, 0.2), p(M, B), p(L, B), p(L, T)],
+        &[p(M, 0.2), p(R, B)],
+    ],
+    // R
+    &[&[p(L, B), p(L, T), p(R, T), p(R, M), p(L, M), p(R, B)]],
+    // S
+    &[&[p(R, T), p(L, T), p(L, M), p(R, M), p(R, B), p(L, B)]],
+    // T
+    &[&[p(L, T), p(R, T)], &[p(M, T), p(M, B)]],
+    // U
+    &[&[p(L, T), p(L, B), p(R, B), p(R, T)]],
+    // V
+    &[&[p(L, T), p(M, B), p(R, T)]],
+    // W
+    &[&[p(L, T), p(L, B), p(M, M), p(R, B), p(R, T)]],
+    // X
+    &[&[p(L, T), p(R, B)], &[p(R, T), p(L, B)]],
+    // Y
+    &[&[p(L, T), p(M, M), p(R, T)], &[p(M, M), p(M, B)]],
+    // Z
+    &[&[p(L, T), p(R, T), p(L, B), p(R, B)]],
+];
+
+/// The lines of `c`, letters in either case come out as capitals. Nothing for a space
+/// or a character without a glyph, both still take up room in a line.
+pub(crate) fn glyph(c: char) -> Option<Glyph> {
+    const COLON: Glyph = &[&[p(M, -0.25), p(M, -0.15)], &[p(M, 0.15), p(M, 0.25)]];
+    const TIMES: Glyph = &[&[p(-0.3, -0.3), p(0.3, 0.3)], &[p(-0.3, 0.3), p(0.3, -0.3)]];
+    const MINUS: Glyph = &[&[p(-0.3, M), p(0.3, M)]];
+    const PLUS: Glyph = &[&[p(-0.3, M), p(0.3, M)], &[p(M, -0.3), p(M, 0.3)]];
+    const PERIOD: Glyph = &[&[p(M, 0.4), p(M, B)]];
+    const COMMA: Glyph = &[&[p(M, 0.3), p(-0.1, B)]];
+    const EXCLAMATION: Glyph = &[&[p(M, T), p(M, 0.2)], &[p(M, 0.4), p(M, B)]];
+    const QUESTION: Glyph = &[
+        &[p(L, -0.25), p(L, T), p(R, T), p(R, M), p(M, M), p(M, 0.2)],
+        &[p(M, 0.4), p(M, B)],
+    ];
+    const SLASH: Glyph = &[&[p(R, T), p(L, B)]];
+    const GREATER: Glyph = &[&[p(L, T), p(R, M), p(L, B)]];
+    const LESS: Glyph = &[&[p(R, T), p(L, M), p(R, B)]];
+
+    match c {
+        '0'..='9' => Some(DIGITS[c as usize - '0' as usize]),
+        'A'..='Z' => Some(LETTERS[c as usize - 'A' as usize]),
+        'a'..='z' => Some(LETTERS[c as usize - 'a' as usize]),
+        ':' => Some(COLON),
+        '×' => Some(TIMES),
+        '-' => Some(MINUS),
+        '+' => Some(PLUS),
+        '.' => Some(PERIOD),
+        ',' => Some(COMMA),
+        '!' => Some(EXCLAMATION),
+        '?' => Some(QUESTION),
+        '/' => Some(SLASH),
+        '>' => Some(GREATER),
+        '<' => Some(LESS),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_letter_and_digit_has_a_glyph_inside_its_box() {
+        for c in ('A'..='Z').chain('a'..='z').chain('0'..='9') {
+            let glyph = glyph(c).unwrap();
+            for point in glyph.iter().flat_map(|line| line.iter()) {
+                assert!(point.x.abs() <= 0.5 && point.y.abs() <= 0.5, "{}", c);
+            }
+        }
+        assert!(glyph(' ').is_none());
+    }
+}
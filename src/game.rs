@@ -0,0 +1,3682 @@
+use std::time::SystemTime;
+
+use ::rand::Rng;
+use macroquad::prelude::*;
+use rand_xoshiro::{rand_core::SeedableRng, Xoshiro256PlusPlus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievement::{Achievement, Achievements, Progress, TOAST_TIME},
+    alien::{Alien, AlienSize},
+    config::{Config, WallHit, WrapMode},
+    daily,
+    difficulty::Skill,
+    ghost::GhostTrack,
+    gravity::GravityWell,
+    grid::SpatialGrid,
+    highscore::HighScore,
+    input::{mouse_playfield_position, FrameInput},
+    options::update_options,
+    particle::{
+        generate_stars, spawn_debris, spawn_exhaust, spawn_score_popup, spawn_text_popup,
+        spawn_trail, splat_dots, splat_lines, ParticlePool, ParticleType, Star,
+    },
+    powerup::{PowerUp, PowerUpKind},
+    projectile::{Owner, Projectile, ProjectileKind, ProjectileState},
+    rock::{collide_rocks, collision_cell_size, Rock, RockSize, SpawnPattern},
+    save,
+    settings::{Density, FlightMode, RunSettings, Settings},
+    ship::{DeathTime, Player, Ship, ShipStatus, Weapon},
+    shop::{update_shop, Upgrades, SHOP_LOCKOUT},
+    sound::{positional_volume, silence, AudioSink, SoundEffect},
+    stats::RunStats,
+    telemetry::{RunEvent, RunLog},
+    theme::ThemeKind,
+    SCALE, SIZE,
+};
+
+pub(crate) const VOLUME_STEP: f32 = 0.1;
+const MAX_SHAKE: f32 = 16.0;
+const ALIEN_SHAKE: f32 = 6.0;
+const SHIP_SHAKE: f32 = 12.0;
+const ALIEN_SHOCKWAVE: f32 = 1.5;
+const SHOCKWAVE_RADIUS: f32 = SCALE * 5.0;
+const MAX_ROCK_SPEED: f32 = 6.0;
+const EXTRA_LIFE_SCORE: usize = 10000;
+pub(crate) const LIFES: usize = 3;
+pub(crate) const MAX_LIFES: usize = 6;
+const BOSS_SCORE: usize = 25000;
+const BOSS_HEALTH: u32 = 12;
+// chance a destroyed rock leaves a power-up behind
+const POWER_UP_CHANCE: f32 = 0.04;
+// how slow the world gets right after picking up bullet time
+const BULLET_TIME_SCALE: f32 = 0.3;
+// seconds it takes to get back to full speed
+const BULLET_TIME_RECOVERY: f32 = 4.0;
+// seconds a weapon upgrade lasts
+const WEAPON_DURATION: f32 = 10.0;
+// seconds rapid fire lasts, short since it clears the field quickly
+const RAPID_FIRE_DURATION: f32 = 6.0;
+// seconds covered by a single frame step
+pub(crate) const FRAME_STEP: f32 = 1.0 / 60.0;
+const MAGNET_DURATION: f32 = 10.0;
+
+/// Everything about a run, saved and restored by `save`. What belongs to the session
+/// instead, like the config, the settings and the audio device, is skipped.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct State {
+    pub(crate) game_state: GameState,
+    #[serde(skip)]
+    pub(crate) config: Config,
+    #[serde(skip)]
+    pub(crate) high_score: usize,
+    pub(crate) endless_high_score: usize,
+    // the best of the daily challenge being played
+    pub(crate) daily_high_score: usize,
+    // whether the run that just ended beat the previous high score
+    pub(crate) new_high_score: bool,
+    // all of the bests as stored, the daily ones of every day included
+    #[serde(skip)]
+    pub(crate) high_scores: HighScore,
+    pub(crate) now: f32,
+    pub(crate) stage_start: f32,
+    // shots fired by the start of the current wave
+    pub(crate) stage_shots: u32,
+    // starts at 1 and goes up every time the field is cleared
+    pub(crate) wave: usize,
+    // when the current run began, survives losing a life
+    pub(crate) run_start: f32,
+    pub(crate) delta: f32,
+    // one in a regular game, two in co-op
+    pub(crate) players: Vec<Player>,
+    pub(crate) rocks: Vec<Rock>,
+    pub(crate) particles: ParticlePool,
+    pub(crate) projectiles: Vec<Projectile>,
+    pub(crate) aliens: Vec<Alien>,
+    pub(crate) power_ups: Vec<PowerUp>,
+    pub(crate) gravity_well: Option<GravityWell>,
+    // slows down everything but the ship, 1.0 is normal speed
+    pub(crate) time_scale: f32,
+    // how far the last stand slow motion has blended in, from 0.0 to 1.0
+    pub(crate) last_stand: f32,
+    pub(crate) weapon: Weapon,
+    pub(crate) weapon_expires: f32,
+    // shots come quicker and more of them may be around until then, stacks with the weapon
+    pub(crate) rapid_fire_until: f32,
+    // until then power-ups and score popups are drawn to the ships
+    pub(crate) magnet_until: f32,
+    pub(crate) seed: u64,
+    pub(crate) random: Xoshiro256PlusPlus,
+    pub(crate) lifes: usize,
+    pub(crate) score: usize,
+    pub(crate) last_score: usize,
+    #[serde(skip, default = "silence")]
+    pub(crate) audio: Box<dyn AudioSink>,
+    pub(crate) bloop: usize,
+    // when the next bloop is due
+    pub(crate) next_bloop: f32,
+    pub(crate) frame: usize,
+    #[serde(skip)]
+    pub(crate) volume: f32,
+    #[serde(skip)]
+    pub(crate) music_volume: f32,
+    // how far each music track is faded in, 0 to 1, before any of the volumes apply
+    #[serde(skip)]
+    pub(crate) music: MusicMix,
+    // whether there are music tracks to play at all, the options hide its volume otherwise
+    #[serde(skip)]
+    pub(crate) music_available: bool,
+    #[serde(skip)]
+    pub(crate) muted: bool,
+    #[serde(skip)]
+    pub(crate) debug: bool,
+    // with the debug overlay, every collision circle and the ships' outlines
+    #[serde(skip)]
+    pub(crate) debug_hitboxes: bool,
+    // shades the collision grid by how many rocks are in each cell
+    #[serde(skip)]
+    pub(crate) debug_heatmap: bool,
+    // a line from everything that moves along its velocity
+    #[serde(skip)]
+    pub(crate) debug_velocities: bool,
+    // with the debug overlay, the simulation only advances one step per press of `.`
+    #[serde(skip)]
+    pub(crate) frame_step: bool,
+    // arrows at the edge of the playfield pointing towards far away aliens
+    #[serde(skip)]
+    pub(crate) alien_indicators: bool,
+    // aliens show up as the score grows, off leaves only the rocks
+    #[serde(skip)]
+    pub(crate) aliens_enabled: bool,
+    #[serde(skip)]
+    pub(crate) theme: ThemeKind,
+    // picked by the player, only used once the next field is generated
+    #[serde(skip)]
+    pub(crate) density: Density,
+    pub(crate) density_changed: f32,
+    // what the current field was generated with
+    pub(crate) field_density: Density,
+    #[serde(skip)]
+    pub(crate) flight_mode: FlightMode,
+    // picked by the player, only used once the next run starts
+    #[serde(skip)]
+    pub(crate) start_lifes: usize,
+    pub(crate) shake: f32,
+    pub(crate) shake_offset: Vec2,
+    #[serde(skip)]
+    pub(crate) fullscreen: bool,
+    #[serde(skip)]
+    pub(crate) last_fullscreen_toggle: f32,
+    pub(crate) stars: Vec<Star>,
+    pub(crate) combo: Combo,
+    pub(crate) stats: RunStats,
+    pub(crate) skill: Skill,
+    pub(crate) control_scheme: ControlScheme,
+    // practice without lives or score, rocks and aliens are spawned by hand
+    pub(crate) sandbox: bool,
+    // relaxed play where the ships can't be destroyed, with a score but no game over
+    pub(crate) zen: bool,
+    pub(crate) run_mode: RunMode,
+    // the day of the daily challenge being played, everyone gets the same seed that day
+    pub(crate) daily: Option<u64>,
+    // the player's own config and settings, put aside while a daily challenge plays
+    // with the defaults
+    #[serde(skip)]
+    pub(crate) daily_stash: Option<(Config, Settings)>,
+    // the input is written to or played back from a replay, which only knows the seed
+    // it started with
+    #[serde(skip)]
+    pub(crate) recorded_input: bool,
+    // the drone warning of aliens is looping
+    #[serde(skip)]
+    pub(crate) alien_drone: bool,
+    // the demo played by `AttractInput` is running
+    pub(crate) attract: bool,
+    // seconds the menu went without input
+    pub(crate) menu_idle: f32,
+    // seconds left on the game over screen to continue the run
+    pub(crate) continue_left: f32,
+    // seconds left to press restart a second time and give up the run
+    #[serde(skip)]
+    pub(crate) restart_armed: f32,
+    // the highlighted line of the options screen and where it goes back to
+    pub(crate) options_index: usize,
+    pub(crate) options_return: GameState,
+    // ore collected this run and not spent yet, along with what was bought with it
+    pub(crate) credits: usize,
+    pub(crate) upgrades: Upgrades,
+    // the highlighted line of the shop and how the last purchase went
+    pub(crate) shop_index: usize,
+    #[serde(skip)]
+    pub(crate) shop_message: &'static str,
+    // counts down after the shop opens, keys still held from the wave don't buy anything
+    #[serde(skip)]
+    pub(crate) shop_lockout: f32,
+    // a recorded run drawn alongside to race against
+    #[serde(skip)]
+    pub(crate) ghost: Option<GhostTrack>,
+    #[serde(skip)]
+    pub(crate) show_ghost: bool,
+    // what happens in the runs, written to a CSV file when asked for
+    #[serde(skip)]
+    pub(crate) run_log: RunLog,
+    #[serde(skip)]
+    pub(crate) achievements: Achievements,
+    // unlocks to announce, each with the time its notification comes up
+    #[serde(skip)]
+    pub(crate) toasts: Vec<(Achievement, f32)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum GameState {
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+    Options,
+    // between two waves with the shop turned on, the ore of the run buys upgrades
+    Shop,
+}
+
+/// How a run treats a lost ship
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum RunMode {
+    // lives, and a game over once they are gone
+    Arcade,
+    // no lives, a lost ship costs score and the run goes on
+    Endless,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ControlScheme {
+    Keyboard,
+    Mouse,
+    // faces the mouse like `Mouse`, the movement keys thrust along the screen's axes
+    TwinStick,
+}
+
+impl State {
+    /// Without a `seed` the clock is used, pass one to reproduce a run
+    pub(crate) fn new(
+        audio: Box<dyn AudioSink>,
+        settings: Settings,
+        config: Config,
+        seed: Option<u64>,
+    ) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .expect("We should be after 1970")
+                .as_secs()
+        });
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(seed);
+        let stars = generate_stars(&mut random);
+        let particles = ParticlePool::with_limit(config.max_particles);
+        Self {
+            game_state: GameState::Menu,
+            config,
+            high_score: 0,
+            endless_high_score: 0,
+            daily_high_score: 0,
+            new_high_score: false,
+            high_scores: HighScore::default(),
+            now: 0.0,
+            stage_start: 0.0,
+            stage_shots: 0,
+            wave: 1,
+            run_start: 0.0,
+            delta: 0.0,
+            players: vec![],
+            rocks: vec![],
+            particles,
+            projectiles: vec![],
+            aliens: vec![],
+            power_ups: vec![],
+            gravity_well: None,
+            time_scale: 1.0,
+            last_stand: 0.0,
+            seed,
+            weapon: Weapon::Single,
+            weapon_expires: 0.0,
+            rapid_fire_until: 0.0,
+            magnet_until: 0.0,
+            random,
+            lifes: settings.lives.clamp(1, MAX_LIFES),
+            score: 0,
+            last_score: 0,
+            audio,
+            bloop: 0,
+            next_bloop: 0.0,
+            frame: 0,
+            volume: settings.volume.clamp(0.0, 1.0),
+            music_volume: settings.music_volume.clamp(0.0, 1.0),
+            music: MusicMix::default(),
+            music_available: false,
+            muted: settings.muted,
+            debug: false,
+            debug_hitboxes: false,
+            debug_heatmap: false,
+            debug_velocities: false,
+            frame_step: false,
+            alien_indicators: settings.alien_indicators,
+            aliens_enabled: settings.aliens,
+            theme: settings.theme,
+            density: settings.density,
+            density_changed: f32::MIN,
+            field_density: settings.density,
+            flight_mode: settings.flight_mode,
+            start_lifes: settings.lives.clamp(1, MAX_LIFES),
+            shake: 0.0,
+            shake_offset: Vec2::ZERO,
+            fullscreen: false,
+            last_fullscreen_toggle: 0.0,
+            stars,
+            combo: Combo::default(),
+            stats: RunStats::default(),
+            skill: Skill::default(),
+            control_scheme: ControlScheme::Keyboard,
+            sandbox: false,
+            zen: false,
+            run_mode: RunMode::Arcade,
+            daily: None,
+            daily_stash: None,
+            recorded_input: false,
+            alien_drone: false,
+            attract: false,
+            menu_idle: 0.0,
+            continue_left: 0.0,
+            restart_armed: 0.0,
+            options_index: 0,
+            options_return: GameState::Menu,
+            credits: 0,
+            upgrades: Upgrades::default(),
+            shop_index: 0,
+            shop_message: "",
+            shop_lockout: 0.0,
+            ghost: None,
+            show_ghost: true,
+            run_log: RunLog::default(),
+            // loaded by the game itself, see `Achievements::persistent`
+            achievements: Achievements::default(),
+            toasts: vec![],
+        }
+    }
+
+    pub(crate) fn player_count(&self) -> usize {
+        if self.config.coop {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// The first player's ship, the one sounds are heard from
+    pub(crate) fn ship(&self) -> &Ship {
+        &self.players[0].ship
+    }
+
+    pub(crate) fn shares_lifes(&self) -> bool {
+        self.players.len() == 1 || self.config.shared_lives
+    }
+
+    /// The lives the ship of player `index` respawns from
+    pub(crate) fn lifes(&self, index: usize) -> usize {
+        if self.shares_lifes() {
+            self.lifes
+        } else {
+            self.players[index].lifes
+        }
+    }
+
+    fn lifes_mut(&mut self, index: usize) -> &mut usize {
+        if self.shares_lifes() {
+            &mut self.lifes
+        } else {
+            &mut self.players[index].lifes
+        }
+    }
+
+    pub(crate) fn effective_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume
+        }
+    }
+
+    /// How long a destroyed ship takes to come back, nothing in zen mode where none is
+    pub(crate) fn respawn_delay(&self) -> Option<f32> {
+        (!self.zen).then_some(self.config.respawn_delay)
+    }
+
+    /// Starts the random numbers over from `seed`, what happens from here on is the same
+    /// as in any other state reseeded with it
+    pub(crate) fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.random = Xoshiro256PlusPlus::seed_from_u64(seed);
+    }
+
+    /// Runs without a game over are left from the pause screen instead
+    pub(crate) fn can_leave(&self) -> bool {
+        self.sandbox || self.zen || self.run_mode == RunMode::Endless
+    }
+
+    /// Whether the settings that change how a run plays out are held as they are. A
+    /// daily challenge is the same game for everyone, so it can't be changed midway, and
+    /// a replay only knows the settings its recording started with.
+    pub(crate) fn run_settings_locked(&self) -> bool {
+        self.daily.is_some() || self.recorded_input
+    }
+
+    /// The best score of the current run mode, or of today's challenge
+    pub(crate) fn best_score(&self) -> usize {
+        if self.daily.is_some() {
+            return self.daily_high_score;
+        }
+        match self.run_mode {
+            RunMode::Arcade => self.high_score,
+            RunMode::Endless => self.endless_high_score,
+        }
+    }
+
+    /// Frame stepping only works along with the debug overlay
+    pub(crate) fn frame_stepping(&self) -> bool {
+        self.debug && self.frame_step
+    }
+
+    pub(crate) fn play(&self, sound: SoundEffect) {
+        self.audio.play(sound, self.effective_volume());
+    }
+
+    /// Plays a sound attenuated by its distance to the ship
+    pub(crate) fn play_positional(&self, sound: SoundEffect, position: Vec2) {
+        let volume = positional_volume(
+            self.effective_volume(),
+            position,
+            self.ship().position,
+            self.config.wrap_mode,
+        );
+        self.audio.play(sound, volume);
+    }
+
+    pub(crate) fn save_settings(&self) {
+        self.settings().save();
+    }
+
+    /// Takes over the bests from the disk, only the game itself does so and saves them
+    pub(crate) fn load_high_scores(&mut self) {
+        self.high_scores = HighScore::load();
+        self.high_score = self.high_scores.score;
+        self.endless_high_score = self.high_scores.endless;
+    }
+
+    /// What `save_settings` writes, the settings as they are now. During a daily
+    /// challenge those that change the run are the player's own, not its defaults.
+    pub(crate) fn settings(&self) -> Settings {
+        let mut settings = Settings {
+            volume: self.volume,
+            music_volume: self.music_volume,
+            muted: self.muted,
+            alien_indicators: self.alien_indicators,
+            aliens: self.aliens_enabled,
+            theme: self.theme,
+            density: self.density,
+            flight_mode: self.flight_mode,
+            lives: self.start_lifes,
+        };
+        if let Some((_, stashed)) = &self.daily_stash {
+            RunSettings::of(stashed).apply(&mut settings);
+        }
+        settings
+    }
+
+    /// Takes over the settings that change how a run plays out
+    fn use_run_settings(&mut self, settings: &Settings) {
+        self.density = settings.density;
+        self.aliens_enabled = settings.aliens;
+        self.flight_mode = settings.flight_mode;
+        self.start_lifes = settings.lives.clamp(1, MAX_LIFES);
+    }
+}
+
+/// Drones on quietly from the moment an alien shows up until the last one is gone
+fn update_alien_drone(state: &mut State) {
+    const DRONE_VOLUME: f32 = 0.3;
+
+    let aliens_around = !state.aliens.is_empty();
+    if aliens_around && !state.alien_drone {
+        let volume = state.effective_volume() * DRONE_VOLUME;
+        state.audio.play_looped(SoundEffect::AlienDrone, volume);
+        state.alien_drone = true;
+    } else if !aliens_around {
+        stop_alien_drone(state);
+    }
+}
+
+fn stop_alien_drone(state: &mut State) {
+    if state.alien_drone {
+        state.audio.stop(SoundEffect::AlienDrone);
+        state.alien_drone = false;
+    }
+}
+
+/// How loud the two music tracks currently are relative to each other
+#[derive(Default)]
+pub(crate) struct MusicMix {
+    started: bool,
+    menu: f32,
+    game: f32,
+}
+
+/// Fades towards the track that fits the screen, the calm one around the menu and the
+/// other one during a run. Both keep looping silently, so a track picks up where it was.
+/// Without the music assets the sink just has nothing to play.
+pub(crate) fn update_music(state: &mut State, delta: f32) {
+    // seconds for a full fade from one track to the other
+    const CROSSFADE: f32 = 1.5;
+    // what is left of the game track while paused
+    const PAUSE_DUCK: f32 = 0.3;
+
+    if !state.music.started {
+        state.audio.play_looped(SoundEffect::MenuMusic, 0.0);
+        state.audio.play_looped(SoundEffect::GameMusic, 0.0);
+        state.music.started = true;
+    }
+
+    let in_run = match state.game_state {
+        GameState::Menu | GameState::GameOver => false,
+        GameState::Playing | GameState::Paused | GameState::Shop => true,
+        GameState::Options => state.options_return != GameState::Menu,
+    };
+    // the demo plays behind the menu, so it keeps the menu's music
+    let (menu_target, game_target) = if !in_run || state.attract {
+        (1.0, 0.0)
+    } else if state.game_state == GameState::Playing {
+        (0.0, 1.0)
+    } else {
+        (0.0, PAUSE_DUCK)
+    };
+
+    let step = delta / CROSSFADE;
+    let fade = |gain: f32, target: f32| gain + (target - gain).clamp(-step, step);
+    state.music.menu = fade(state.music.menu, menu_target);
+    state.music.game = fade(state.music.game, game_target);
+
+    let volume = state.effective_volume() * state.music_volume;
+    state
+        .audio
+        .set_volume(SoundEffect::MenuMusic, state.music.menu * volume);
+    state
+        .audio
+        .set_volume(SoundEffect::GameMusic, state.music.game * volume);
+}
+
+pub(crate) fn stop_music(state: &mut State) {
+    if state.music.started {
+        state.audio.stop(SoundEffect::MenuMusic);
+        state.audio.stop(SoundEffect::GameMusic);
+        state.music = MusicMix::default();
+    }
+}
+
+/// Silences whatever keeps going on its own, the thruster and the drone. Short one-shot
+/// sounds are left to finish.
+pub(crate) fn stop_all_sounds(state: &mut State) {
+    state.audio.stop(SoundEffect::Thruster);
+    stop_alien_drone(state);
+}
+
+/// Leaves out the fragments that would take the field past the rock cap. Their parents
+/// are broken and scored already, only the pieces are lost, and a full field keeps
+/// the rocks it has, so the wave goes on to be cleared like any other.
+fn cap_fragments(state: &State, fragments: &mut Vec<Rock>) {
+    let remaining = state.rocks.iter().filter(|rock| !rock.removed).count();
+    let room = state.config.max_rocks.saturating_sub(remaining);
+    if fragments.len() <= room {
+        return;
+    }
+    if state.debug {
+        info!(
+            "Rock cap of {} reached, leaving out {} fragments",
+            state.config.max_rocks,
+            fragments.len() - room
+        );
+    }
+    fragments.truncate(room);
+}
+
+/// Fragments start out where their parent was, maybe on top of the ship that broke it
+fn grant_fragment_grace(fragments: &mut [Rock], now: f32) {
+    for fragment in fragments.iter_mut() {
+        fragment.harmless_until = now + Rock::FRAGMENT_GRACE;
+    }
+}
+
+/// Extra points for a single shot destroying `kills` rocks, nothing for just one
+fn chain_bonus(kills: u32) -> usize {
+    const CHAIN_POINTS: usize = 100;
+    let kills = kills as usize;
+    CHAIN_POINTS * kills * kills.saturating_sub(1)
+}
+
+/// Pays out shots that are used up after breaking several rocks on their way
+fn award_chains(state: &mut State) {
+    let chains: Vec<(u32, Vec2)> = state
+        .projectiles
+        .iter()
+        .filter(|projectile| projectile.kills > 1 && !projectile.is_alive())
+        .map(|projectile| {
+            let center = projectile.kill_positions / projectile.kills as f32;
+            (projectile.kills, center)
+        })
+        .collect();
+    for (kills, center) in chains {
+        let bonus = chain_bonus(kills);
+        state.score += bonus;
+        spawn_text_popup(center, format!("CHAIN x{}", kills), &mut state.particles);
+        spawn_score_popup(center + Vec2::Y * SCALE * 0.6, bonus, &mut state.particles);
+        splat_dots(
+            center,
+            15 * kills as usize,
+            None,
+            &mut state.particles,
+            &mut state.random,
+        );
+        state.play_positional(SoundEffect::Chain, center);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Combo {
+    pub(crate) multiplier: usize,
+    pub(crate) last_kill: f32,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            multiplier: 1,
+            last_kill: f32::MIN,
+        }
+    }
+}
+
+impl Combo {
+    // seconds after a kill in which the next one keeps the combo going
+    const WINDOW: f32 = 1.5;
+    // kills closer together than this (e.g. fragments of the same rock) only count once
+    const MIN_GAP: f32 = 0.2;
+    const MAX_MULTIPLIER: usize = 8;
+
+    /// Registers a kill at `now` and returns the multiplier it is scored with
+    pub(crate) fn register(&mut self, now: f32) -> usize {
+        let since_last = now - self.last_kill;
+        if since_last > Self::WINDOW {
+            self.multiplier = 1;
+            self.last_kill = now;
+        } else if since_last >= Self::MIN_GAP {
+            self.multiplier = (self.multiplier + 1).min(Self::MAX_MULTIPLIER);
+            self.last_kill = now;
+        }
+        self.multiplier
+    }
+
+    pub(crate) fn update(&mut self, now: f32) {
+        if now - self.last_kill > Self::WINDOW {
+            self.multiplier = 1;
+        }
+    }
+}
+
+fn update_audio_settings(state: &mut State) {
+    let keys_pressed = get_keys_pressed();
+    let mut changed = false;
+    if keys_pressed.contains(&KeyCode::Equal) || keys_pressed.contains(&KeyCode::KpAdd) {
+        state.volume = (state.volume + VOLUME_STEP).min(1.0);
+        changed = true;
+    }
+    if keys_pressed.contains(&KeyCode::Minus) || keys_pressed.contains(&KeyCode::KpSubtract) {
+        state.volume = (state.volume - VOLUME_STEP).max(0.0);
+        changed = true;
+    }
+    if keys_pressed.contains(&KeyCode::M) {
+        state.muted = !state.muted;
+        changed = true;
+    }
+    if changed {
+        state.save_settings();
+    }
+}
+
+/// Stops between two waves, the new one is already laid out behind the shop
+fn open_shop(state: &mut State) {
+    state.shop_index = 0;
+    state.shop_message = "";
+    state.shop_lockout = SHOP_LOCKOUT;
+    state.game_state = GameState::Shop;
+    for player in state.players.iter_mut() {
+        player.render_thruster_plume = false;
+    }
+    stop_all_sounds(state);
+}
+
+/// Shows the options on top of the menu or the pause screen, whichever is open
+fn open_options(state: &mut State) {
+    state.options_return = state.game_state;
+    state.options_index = 0;
+    state.game_state = GameState::Options;
+}
+
+fn any_input_pressed() -> bool {
+    !get_keys_pressed().is_empty()
+        || is_mouse_button_pressed(MouseButton::Left)
+        || is_mouse_button_pressed(MouseButton::Right)
+}
+
+/// Starts today's challenge, the same rocks and aliens for everyone playing it
+fn start_daily(state: &mut State) {
+    let day = daily::today();
+    state.daily = Some(day);
+    state.daily_high_score = state
+        .high_scores
+        .daily
+        .get(&daily::date(day))
+        .copied()
+        .unwrap_or(0);
+    state.reseed(daily::seed(day));
+    state.run_mode = RunMode::Arcade;
+    // the same game for everyone, whatever they set up for their own runs. Already put
+    // aside when the challenge is only started over.
+    if state.daily_stash.is_none() {
+        let config = state.config.for_daily();
+        let config = std::mem::replace(&mut state.config, config);
+        state.daily_stash = Some((config, state.settings()));
+        state.use_run_settings(&Settings::default());
+    }
+    reset_game(state);
+    state.game_state = GameState::Playing;
+}
+
+/// Gives the player's own config and settings back once the daily challenge is left
+fn end_daily(state: &mut State) {
+    state.daily = None;
+    if let Some((config, settings)) = state.daily_stash.take() {
+        state.config = config;
+        state.use_run_settings(&settings);
+    }
+}
+
+/// Starts a new run of the same kind right away, without going through the menu.
+/// A run given up this way still gets its score recorded.
+fn quick_restart(state: &mut State) {
+    // neither the sandbox, a zen run nor the demo has a score that counts
+    let counts = !state.sandbox && !state.zen && !state.attract;
+    if counts && state.game_state != GameState::GameOver {
+        record_high_score(state);
+    }
+    state.restart_armed = 0.0;
+    state.continue_left = 0.0;
+    if state.daily.is_some() {
+        // from the same seed again, or the next day's if it changed meanwhile
+        start_daily(state);
+    } else {
+        reset_game(state);
+    }
+    state.game_state = GameState::Playing;
+}
+
+/// A run still going is only restarted by a second press shortly after the first,
+/// so a stray key doesn't throw away a good one. Returns whether it restarted.
+fn update_restart(state: &mut State) -> bool {
+    const DOUBLE_PRESS_TIME: f32 = 1.0;
+
+    state.restart_armed = (state.restart_armed - get_frame_time()).max(0.0);
+    if !is_key_pressed(state.config.key_bindings.restart.0) {
+        return false;
+    }
+    if state.restart_armed > 0.0 {
+        quick_restart(state);
+        return true;
+    }
+    state.restart_armed = DOUBLE_PRESS_TIME;
+    false
+}
+
+/// Ends the demo, the menu gets a fresh game again
+fn stop_attract(state: &mut State) {
+    state.attract = false;
+    state.run_log.muted = false;
+    // the demo drew from the random numbers of the run. Drawn again from the seed like
+    // at startup, so a recording made after it plays back on the seed in its header.
+    state.reseed(state.seed);
+    state.stars = generate_stars(&mut state.random);
+    reset_game(state);
+    state.game_state = GameState::Menu;
+}
+
+/// Moves between menu, gameplay, pause and game over. The pause is toggled with P or Escape
+/// and also kicks in by itself when the window seems to have lost focus.
+/// Returns whether the simulation should advance this frame.
+pub(crate) fn update_game_state(state: &mut State) -> bool {
+    // Macroquad doesn't report focus changes, but a hidden or minimized window stops
+    // getting frames, so a frame this long means the player was away
+    const FOCUS_LOST_FRAME_TIME: f32 = 0.5;
+    // seconds in the menu without input before the demo starts
+    const ATTRACT_DELAY: f32 = 10.0;
+
+    if state.attract {
+        // any real input, or the demo ship running out of lives, ends it
+        if any_input_pressed() || state.game_state == GameState::GameOver {
+            stop_attract(state);
+            return false;
+        }
+        return true;
+    }
+
+    let toggle =
+        is_key_pressed(state.config.key_bindings.pause.0) || is_key_pressed(KeyCode::Escape);
+    match state.game_state {
+        // The field behind the menu is always a fresh game, see the game over branch
+        GameState::Menu => {
+            if is_key_pressed(KeyCode::Space) {
+                state.run_mode = RunMode::Arcade;
+                state.game_state = GameState::Playing;
+            } else if is_key_pressed(KeyCode::E) {
+                state.run_mode = RunMode::Endless;
+                state.game_state = GameState::Playing;
+            } else if is_key_pressed(KeyCode::D) {
+                // it reseeds, a replay of it would play on the seed it was recorded with
+                if state.recorded_input {
+                    warn!("The daily challenge can't be played while recording");
+                } else {
+                    start_daily(state);
+                }
+            } else if is_key_pressed(KeyCode::S) {
+                state.sandbox = true;
+                state.game_state = GameState::Playing;
+            } else if is_key_pressed(KeyCode::Z) {
+                state.zen = true;
+                state.game_state = GameState::Playing;
+            } else if is_key_pressed(KeyCode::O) {
+                open_options(state);
+            } else if any_input_pressed() {
+                state.menu_idle = 0.0;
+            } else {
+                state.menu_idle += get_frame_time();
+                if state.menu_idle > ATTRACT_DELAY {
+                    state.menu_idle = 0.0;
+                    state.attract = true;
+                    state.run_log.muted = true;
+                    state.game_state = GameState::Playing;
+                }
+            }
+            // starts with the next frame, so the key press doesn't fire a shot as well
+            return false;
+        }
+        // `now` stands still behind the game over screen, so the countdown runs on frame time
+        GameState::GameOver => {
+            state.continue_left = (state.continue_left - get_frame_time()).max(0.0);
+            if is_key_pressed(KeyCode::Space) {
+                if state.continue_left > 0.0 {
+                    continue_game(state);
+                } else if state.daily.is_some() {
+                    // from the same seed again, or the next day's if it changed meanwhile
+                    start_daily(state);
+                } else {
+                    reset_game(state);
+                }
+                state.game_state = GameState::Playing;
+            } else if is_key_pressed(state.config.key_bindings.restart.0) {
+                // gives up on continuing
+                quick_restart(state);
+            } else if is_key_pressed(KeyCode::Escape) {
+                end_daily(state);
+                reset_game(state);
+                state.game_state = GameState::Menu;
+            }
+            return false;
+        }
+        GameState::Playing => {
+            let focus_lost = state.frame > 0 && get_frame_time() > FOCUS_LOST_FRAME_TIME;
+            if update_restart(state) {
+                return false;
+            }
+            if toggle || focus_lost {
+                state.game_state = GameState::Paused;
+                for player in state.players.iter_mut() {
+                    player.render_thruster_plume = false;
+                }
+                stop_all_sounds(state);
+            }
+        }
+        // Only resumed on request, never just because the window got its focus back
+        GameState::Paused => {
+            if update_restart(state) {
+                return false;
+            }
+            if toggle {
+                state.game_state = GameState::Playing;
+            } else if is_key_pressed(KeyCode::O) {
+                open_options(state);
+            } else if state.can_leave() && is_key_pressed(KeyCode::Q) {
+                // the only way an endless run ends
+                if state.run_mode == RunMode::Endless && !state.sandbox {
+                    record_high_score(state);
+                }
+                state.sandbox = false;
+                state.zen = false;
+                state.run_mode = RunMode::Arcade;
+                end_daily(state);
+                reset_game(state);
+                state.game_state = GameState::Menu;
+            }
+        }
+        GameState::Options => {
+            update_options(state);
+            // the menu's idle time starts over once back
+            state.menu_idle = 0.0;
+        }
+        GameState::Shop => update_shop(state),
+    }
+    state.game_state == GameState::Playing
+}
+
+/// Keys that change settings or, in the sandbox, the playfield. They are read straight
+/// from the keyboard, so unlike `update` this needs a window.
+pub(crate) fn update_hotkeys(state: &mut State) {
+    update_audio_settings(state);
+    if is_key_pressed(KeyCode::F3) {
+        state.debug = !state.debug;
+    }
+    if state.debug && is_key_pressed(KeyCode::F4) {
+        state.debug_hitboxes = !state.debug_hitboxes;
+    }
+    if state.debug && is_key_pressed(KeyCode::F6) {
+        state.debug_heatmap = !state.debug_heatmap;
+    }
+    if state.debug && is_key_pressed(KeyCode::F8) {
+        state.debug_velocities = !state.debug_velocities;
+    }
+    if state.debug && is_key_pressed(KeyCode::F7) {
+        state.frame_step = !state.frame_step;
+    }
+    if state.ghost.is_some() && is_key_pressed(KeyCode::G) {
+        state.show_ghost = !state.show_ghost;
+    }
+    if is_key_pressed(KeyCode::I) {
+        state.alien_indicators = !state.alien_indicators;
+        state.save_settings();
+    }
+    if is_key_pressed(KeyCode::T) {
+        state.theme = state.theme.next();
+        state.save_settings();
+    }
+    if is_key_pressed(KeyCode::F5) {
+        match save::save(state, save::SAVE_PATH) {
+            Ok(()) => info!("Saved the run to {}", save::SAVE_PATH),
+            Err(err) => warn!("Unable to save the run: {}", err),
+        }
+    }
+    // only over a run, and not while a replay is written or played as it wouldn't know
+    let in_run = matches!(state.game_state, GameState::Playing | GameState::Paused);
+    if in_run && !state.recorded_input && is_key_pressed(KeyCode::F9) {
+        if let Err(err) = save::load(state, save::SAVE_PATH) {
+            warn!("Unable to load the run: {}", err);
+        }
+    }
+    if !state.run_settings_locked() && is_key_pressed(KeyCode::N) {
+        state.density = state.density.next();
+        state.density_changed = state.now;
+        state.save_settings();
+    }
+    if state.sandbox {
+        update_sandbox(state);
+    }
+    update_fullscreen(state);
+}
+
+/// The time the next `update` covers, nothing while frame stepping waits for a press of `.`.
+/// A step always covers the same fixed time, so stepping through a moment is repeatable.
+pub(crate) fn step_delta(state: &State, delta: f32) -> Option<f32> {
+    if !state.frame_stepping() {
+        Some(delta)
+    } else if is_key_pressed(KeyCode::Period) {
+        Some(FRAME_STEP)
+    } else {
+        None
+    }
+}
+
+/// Starts a frame lasting `delta` seconds. A hitch (dragging the window, a breakpoint)
+/// only counts as `MAX_DELTA`, so the game slows down for a moment instead of moving
+/// everything and running every timer down in one go.
+pub(crate) fn advance_clock(state: &mut State, delta: f32) {
+    const MAX_DELTA: f32 = 1.0 / 20.0;
+
+    state.delta = delta.min(MAX_DELTA);
+    state.now += state.delta;
+}
+
+/// Advances the simulation by `state.delta`. Everything it needs from the outside comes
+/// through `inputs`, one entry per player with missing ones treated as idle, so it also
+/// runs headless.
+pub(crate) fn update(state: &mut State, inputs: &[FrameInput]) {
+    if inputs
+        .first()
+        .is_some_and(|input| input.toggle_control_scheme)
+    {
+        state.control_scheme = match state.control_scheme {
+            ControlScheme::Keyboard => ControlScheme::Mouse,
+            ControlScheme::Mouse => ControlScheme::TwinStick,
+            ControlScheme::TwinStick => ControlScheme::Keyboard,
+        };
+    }
+
+    for index in 0..state.players.len() {
+        let input = inputs.get(index).copied().unwrap_or_default();
+        update_player(state, index, &input);
+    }
+    let all_alive = state
+        .players
+        .iter()
+        .all(|player| (&player.ship.status).into());
+    state.stats.survive(all_alive, state.delta);
+
+    let wrap_mode = state.config.wrap_mode;
+    let hitbox_scale = state.config.ship_hitbox_scale;
+    collide_rocks(&mut state.rocks, wrap_mode);
+
+    let volume = state.effective_volume();
+    let respawn_delay = state.respawn_delay();
+    // sounds in the loops below are heard from where the ship is
+    let listener = state.ship().position;
+    let mut additional_rocks: Vec<Rock> = vec![];
+    let time_scale = state.time_scale;
+    // Neither moves before the projectile loop below, so rocks only look at their neighbours
+    let projectile_grid = SpatialGrid::from_positions(
+        collision_cell_size(),
+        state
+            .projectiles
+            .iter()
+            .map(|projectile| projectile.position),
+    );
+    let alien_grid = SpatialGrid::from_positions(
+        collision_cell_size(),
+        state.aliens.iter().map(|alien| alien.position),
+    );
+    let gravity_well = state.gravity_well;
+    for rock in state.rocks.iter_mut() {
+        if let Some(well) = gravity_well {
+            rock.velocity = (rock.velocity
+                + well.pull(rock.position, wrap_mode) * state.delta * time_scale)
+                .clamp_length_max(MAX_ROCK_SPEED);
+        }
+        rock.position = rock.position + rock.velocity * time_scale;
+        rock.rotation += rock.angular_velocity * time_scale;
+        keep_in_arena(wrap_mode, &mut rock.position, &mut rock.velocity);
+
+        // The well swallows rocks whole, armored or not, without leaving fragments behind.
+        // They are paid once at face value, the combo is only for what the players break.
+        if let Some(well) = gravity_well {
+            if !rock.removed && well.swallows(rock.position, rock.radius(), wrap_mode) {
+                rock.removed = true;
+                let volume = positional_volume(volume, rock.position, listener, wrap_mode);
+                state.audio.play(SoundEffect::Asteroid, volume);
+                splat_dots(
+                    rock.position,
+                    10,
+                    None,
+                    &mut state.particles,
+                    &mut state.random,
+                );
+                add_shake(&mut state.shake, rock.size.get_shake());
+                let points = rock.size.get_score();
+                state.score += points;
+                spawn_score_popup(rock.position, points, &mut state.particles);
+                state.stats.rocks_lost += 1;
+                let size = &rock.size;
+                let event = RunEvent::RockDestroyed { size, points };
+                state.run_log.log(state.now, state.wave, event);
+            }
+        }
+
+        // Check for ship v rock collision
+        for player in state.players.iter_mut() {
+            let ship = &mut player.ship;
+            if !rock.removed
+                && rock.can_hit_ship(state.now)
+                && ship.is_vulnerable(state.now)
+                && ship.overlaps_circle(rock.position, rock.radius(), hitbox_scale, wrap_mode)
+            {
+                let impact = rock.velocity - ship.velocity;
+                hit_ship(
+                    ship,
+                    state.now,
+                    HitEffects {
+                        particles: &mut state.particles,
+                        random: &mut state.random,
+                        audio: state.audio.as_ref(),
+                        volume,
+                    },
+                    respawn_delay,
+                    Some(impact),
+                );
+                let new_rocks = hit_rock(
+                    rock,
+                    &mut state.random,
+                    &mut state.particles,
+                    ship.velocity.try_normalize(),
+                    state.audio.as_ref(),
+                    positional_volume(volume, rock.position, listener, wrap_mode),
+                    &mut state.shake,
+                );
+                if rock.removed {
+                    state.stats.rocks_lost += 1;
+                    let size = &rock.size;
+                    let event = RunEvent::RockDestroyed { size, points: 0 };
+                    state.run_log.log(state.now, state.wave, event);
+                }
+                if let Some(mut new_rocks) = new_rocks {
+                    additional_rocks.append(&mut new_rocks);
+                }
+            }
+        }
+
+        // Check for alien v rock collision
+        for index in alien_grid.near(rock.position) {
+            let alien = &mut state.aliens[index];
+            if !alien.removed
+                && !rock.removed
+                && rock.position.distance(alien.position) < rock.radius()
+            {
+                alien.removed = true;
+                let event = RunEvent::AlienDestroyed { size: &alien.size };
+                state.run_log.log(state.now, state.wave, event);
+                let possible_new_rock: Option<Vec<Rock>> = hit_rock(
+                    rock,
+                    &mut state.random,
+                    &mut state.particles,
+                    (alien.direction * alien.size.speed()).try_normalize(),
+                    state.audio.as_ref(),
+                    positional_volume(volume, rock.position, listener, wrap_mode),
+                    &mut state.shake,
+                );
+                if rock.removed {
+                    let points = rock.size.get_score() * state.combo.register(state.now);
+                    state.score += points;
+                    spawn_score_popup(rock.position, points, &mut state.particles);
+                    state.stats.rocks_lost += 1;
+                    let size = &rock.size;
+                    let event = RunEvent::RockDestroyed { size, points };
+                    state.run_log.log(state.now, state.wave, event);
+                }
+                if let Some(mut new_rocks) = possible_new_rock {
+                    additional_rocks.append(&mut new_rocks);
+                }
+            }
+        }
+
+        // Check for projectile v rock collision
+        for index in projectile_grid.near(rock.position) {
+            let projectile = &mut state.projectiles[index];
+            // a rock already broken this frame, e.g. by a bomb, can't be hit again
+            if projectile.is_alive()
+                && !rock.removed
+                && rock.position.distance(projectile.position) < rock.radius()
+            {
+                if projectile.hit() && projectile.owner.is_player() {
+                    state.stats.shots_hit += 1;
+                }
+                let possible_new_rock: Option<Vec<Rock>> = hit_rock(
+                    rock,
+                    &mut state.random,
+                    &mut state.particles,
+                    projectile.velocity.try_normalize(),
+                    state.audio.as_ref(),
+                    positional_volume(volume, rock.position, listener, wrap_mode),
+                    &mut state.shake,
+                );
+                if rock.removed && projectile.owner.is_player() {
+                    let points = rock.size.get_score() * state.combo.register(state.now);
+                    state.score += points;
+                    spawn_score_popup(rock.position, points, &mut state.particles);
+                    state.stats.rock_destroyed(&rock.size);
+                    projectile.kills += 1;
+                    projectile.kill_positions += rock.position;
+                    let size = &rock.size;
+                    let event = RunEvent::RockDestroyed { size, points };
+                    state.run_log.log(state.now, state.wave, event);
+                } else if rock.removed {
+                    // by an alien's shot, that's no kill of the players
+                    state.stats.rocks_lost += 1;
+                    let size = &rock.size;
+                    let event = RunEvent::RockDestroyed { size, points: 0 };
+                    state.run_log.log(state.now, state.wave, event);
+                }
+                if let Some(mut new_rocks) = possible_new_rock {
+                    additional_rocks.append(&mut new_rocks);
+                }
+            }
+        }
+    }
+
+    // Stars drift against the ship's movement, closer ones faster
+    const STAR_PARALLAX: f32 = 0.1;
+    let ship_velocity = state.ship().velocity;
+    for star in state.stars.iter_mut() {
+        star.position = keep_in_frame(star.position - ship_velocity * star.depth * STAR_PARALLAX);
+    }
+
+    for particle in state.particles.iter_mut() {
+        particle.position = particle.position + particle.velocity;
+        particle.position = keep_in_frame(particle.position);
+        particle.time_to_live -= state.delta;
+        if let ParticleType::Line(line) = &mut particle.particle_type {
+            line.rotation += line.spin;
+        }
+    }
+
+    for projectile in state.projectiles.iter_mut() {
+        // the player's own shots keep their speed, the ship stays responsive in bullet time
+        let projectile_scale = match projectile.owner {
+            Owner::Player(_) => 1.0,
+            Owner::Alien => time_scale,
+        };
+        let target = nearest_ship(&state.players, projectile.position, wrap_mode);
+        if let (ProjectileKind::Homing, Owner::Alien, Some(target)) =
+            (projectile.kind, projectile.owner, target)
+        {
+            // radians / second, slow enough for the ship to outmaneuver it
+            const HOMING_TURN_RATE: f32 = 1.5;
+            let to_ship = playfield_delta(wrap_mode, projectile.position, target);
+            let difference = wrap_angle(
+                to_ship.y.atan2(to_ship.x) - projectile.velocity.y.atan2(projectile.velocity.x),
+            );
+            let max_turn = HOMING_TURN_RATE * state.delta * projectile_scale;
+            let turn = difference.clamp(-max_turn, max_turn);
+            projectile.velocity = projectile.velocity.rotate(Vec2::from_angle(turn));
+        }
+        if let Some(well) = gravity_well {
+            projectile.velocity += well.pull(projectile.position, wrap_mode) * state.delta;
+            if well.swallows(projectile.position, 0.0, wrap_mode) {
+                projectile.state = ProjectileState::Dead;
+            }
+        }
+        projectile.previous_position = projectile.position;
+        projectile.position = projectile.position + projectile.velocity * projectile_scale;
+        if let ProjectileKind::Ricochet { bounces } = &mut projectile.kind {
+            if bounce_off_edges(&mut projectile.position, &mut projectile.velocity) {
+                if *bounces == 0 {
+                    projectile.state = ProjectileState::Dead;
+                } else {
+                    *bounces -= 1;
+                }
+            }
+        } else {
+            match (wrap_mode, state.config.projectiles_at_walls) {
+                (WrapMode::Wrap, _) => projectile.position = keep_in_frame(projectile.position),
+                (WrapMode::Bounce, WallHit::Bounce) => {
+                    bounce_off_edges(&mut projectile.position, &mut projectile.velocity);
+                }
+                (WrapMode::Bounce, WallHit::Die) => {
+                    if bounce_off_edges(&mut projectile.position, &mut projectile.velocity) {
+                        projectile.state = ProjectileState::Dead;
+                    }
+                }
+            }
+        }
+        if let ProjectileState::Alive { mut time_to_live } = projectile.state {
+            let friendly_fire = state.config.friendly_fire;
+            let self_hit = state.config.self_hit;
+            let hit = state
+                .players
+                .iter_mut()
+                .enumerate()
+                .find(|(index, player)| {
+                    let can_hit = match projectile.owner {
+                        Owner::Alien => true,
+                        Owner::Player(owner) if owner == *index => self_hit,
+                        Owner::Player(_) => friendly_fire,
+                    };
+                    can_hit
+                        && player.ship.is_vulnerable(state.now)
+                        && player.ship.overlaps_circle(
+                            projectile.position,
+                            projectile.radius(),
+                            hitbox_scale,
+                            wrap_mode,
+                        )
+                });
+            if let Some((_, player)) = hit {
+                projectile.state = ProjectileState::Dead;
+                let impact = projectile.velocity - player.ship.velocity;
+                hit_ship(
+                    &mut player.ship,
+                    state.now,
+                    HitEffects {
+                        particles: &mut state.particles,
+                        random: &mut state.random,
+                        audio: state.audio.as_ref(),
+                        volume,
+                    },
+                    respawn_delay,
+                    Some(impact),
+                );
+            } else {
+                time_to_live -= state.delta * projectile_scale;
+                projectile.state = time_to_live.into();
+            }
+
+            for index in alien_grid.near(projectile.position) {
+                let alien = &mut state.aliens[index];
+                if !alien.removed
+                    && projectile.owner.is_player()
+                    && alien.position.distance(projectile.position) < alien.radius()
+                {
+                    if projectile.hit() {
+                        state.stats.shots_hit += 1;
+                    }
+                    alien.removed = true;
+                    state.combo.register(state.now);
+                    state.stats.alien_destroyed(&alien.size);
+                    let event = RunEvent::AlienDestroyed { size: &alien.size };
+                    state.run_log.log(state.now, state.wave, event);
+                }
+            }
+        }
+    }
+
+    // the base timings of `AlienSize` shrink the longer the run lasts
+    let aggression = alien_aggression(state);
+    // every alien steers by all the others, so their positions are taken before any moves
+    let flock: Vec<(usize, Vec2)> = state
+        .aliens
+        .iter()
+        .enumerate()
+        .filter(|(_, alien)| !alien.removed)
+        .map(|(index, alien)| (index, alien.position))
+        .collect();
+    let flocking = state.config.alien_flocking;
+    for (index, alien) in state.aliens.iter_mut().enumerate() {
+        for player in state.players.iter_mut() {
+            if !alien.removed
+                && player.ship.is_vulnerable(state.now)
+                && player.ship.overlaps_circle(
+                    alien.position,
+                    alien.radius(),
+                    hitbox_scale,
+                    wrap_mode,
+                )
+            {
+                alien.removed = true;
+                let event = RunEvent::AlienDestroyed { size: &alien.size };
+                state.run_log.log(state.now, state.wave, event);
+                let impact = alien.direction * alien.size.speed() - player.ship.velocity;
+                hit_ship(
+                    &mut player.ship,
+                    state.now,
+                    HitEffects {
+                        particles: &mut state.particles,
+                        random: &mut state.random,
+                        audio: state.audio.as_ref(),
+                        volume,
+                    },
+                    respawn_delay,
+                    Some(impact),
+                );
+            }
+        }
+
+        if !alien.removed {
+            if (state.now - alien.last_direction) > alien.size.direction_change_time() / aggression
+            {
+                alien.last_direction = state.now;
+                let angle = std::f32::consts::TAU * state.random.gen::<f32>();
+                let wander = Vec2::new(f32::cos(angle), f32::sin(angle));
+                alien.direction = if state.config.alien_flee {
+                    let to_ship = nearest_ship(&state.players, alien.position, wrap_mode)
+                        .map(|ship| playfield_delta(wrap_mode, alien.position, ship));
+                    alien.flee_direction(wander, to_ship)
+                } else {
+                    wander
+                };
+            }
+
+            if flocking > 0.0 && flock.len() > 1 {
+                // the share of a full turn towards the flock's pull in a frame at full strength
+                const FLOCK_RATE: f32 = 0.1;
+                let others: Vec<Vec2> = flock
+                    .iter()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, position)| *position)
+                    .collect();
+                let to_ship = nearest_ship(&state.players, alien.position, wrap_mode)
+                    .map(|ship| playfield_delta(wrap_mode, alien.position, ship));
+                let steering = alien.flock_steering(&others, to_ship, wrap_mode);
+                alien.direction = (alien.direction + steering * flocking * FLOCK_RATE * time_scale)
+                    .try_normalize()
+                    .unwrap_or(alien.direction);
+            }
+
+            alien.position = alien.position + alien.direction * alien.size.speed() * time_scale;
+            keep_in_arena(wrap_mode, &mut alien.position, &mut alien.direction);
+
+            if (state.now - alien.last_shot) > alien.shot_interval(aggression) {
+                alien.last_shot = state.now;
+                // with every ship gone it keeps aiming where the first one was
+                let target = nearest_ship(&state.players, alien.position, wrap_mode)
+                    .unwrap_or(state.players[0].ship.position);
+                let direction = alien.aim_at(target, wrap_mode);
+                // small aliens sometimes launch a slower missile that lives longer instead
+                const HOMING_CHANCE: f32 = 0.25;
+                let homing = matches!(alien.size, AlienSize::Small)
+                    && state.random.gen::<f32>() < HOMING_CHANCE;
+                let (kind, speed, time_to_live) = if homing {
+                    (ProjectileKind::Homing, 3.5, 4.0)
+                } else {
+                    (
+                        ProjectileKind::Straight,
+                        6.0,
+                        state.config.alien_shot_lifetime,
+                    )
+                };
+                let position = alien.position + direction * SCALE * 0.55;
+                state.projectiles.push(Projectile {
+                    position,
+                    previous_position: position,
+                    velocity: direction * speed,
+                    state: ProjectileState::Alive { time_to_live },
+                    owner: Owner::Alien,
+                    kind,
+                    pierce: 0,
+                    landed: false,
+                    kills: 0,
+                    kill_positions: Vec2::ZERO,
+                });
+                state.audio.play(
+                    SoundEffect::Shoot,
+                    positional_volume(volume, alien.position, listener, wrap_mode),
+                );
+            }
+        } else {
+            state.audio.play(
+                SoundEffect::Asteroid,
+                positional_volume(volume, alien.position, listener, wrap_mode),
+            );
+            add_shake(&mut state.shake, ALIEN_SHAKE);
+            splat_dots(
+                alien.position,
+                15,
+                None,
+                &mut state.particles,
+                &mut state.random,
+            );
+            splat_lines(
+                alien.position,
+                4,
+                None,
+                &mut state.particles,
+                &mut state.random,
+            );
+        }
+    }
+
+    // Everything flagged as removed was destroyed this frame and pushes its surroundings
+    let shockwaves: Vec<(Vec2, f32)> = state
+        .rocks
+        .iter()
+        .filter(|rock| rock.removed)
+        .map(|rock| (rock.position, rock.size.get_shockwave()))
+        .chain(
+            state
+                .aliens
+                .iter()
+                .filter(|alien| alien.removed)
+                .map(|alien| (alien.position, ALIEN_SHOCKWAVE)),
+        )
+        .collect();
+
+    for rock in state.rocks.iter().filter(|rock| rock.removed) {
+        // ore only drops with the shop turned on, otherwise there would be nothing to buy
+        const ORE_CHANCE: f32 = 0.08;
+        if state.config.shop && state.random.gen::<f32>() < ORE_CHANCE {
+            state
+                .power_ups
+                .push(PowerUp::new(rock.position, PowerUpKind::Ore));
+        }
+        if state.random.gen::<f32>() < POWER_UP_CHANCE * state.field_density.multiplier() {
+            let kind = match state.random.gen_range(0..7) {
+                0 => PowerUpKind::BulletTime,
+                1 => PowerUpKind::SpreadShot,
+                2 => PowerUpKind::Ricochet,
+                3 => PowerUpKind::Bomb,
+                4 => PowerUpKind::RapidFire,
+                5 => PowerUpKind::Magnet,
+                _ => PowerUpKind::Shield,
+            };
+            state.power_ups.push(PowerUp::new(rock.position, kind));
+        }
+    }
+    update_power_ups(state);
+    update_last_stand(state);
+
+    cap_fragments(state, &mut additional_rocks);
+    grant_fragment_grace(&mut additional_rocks, state.now);
+    state.rocks.append(&mut additional_rocks);
+    apply_shockwaves(&mut state.rocks, &shockwaves, wrap_mode);
+    state.rocks.retain(|rock| !rock.removed);
+    state.particles.free_expired();
+    award_chains(state);
+    state.projectiles.retain(|projectile| projectile.is_alive());
+    state.aliens.retain(|alien| !alien.removed);
+    update_alien_drone(state);
+
+    for index in 0..state.players.len() {
+        let ShipStatus::Dead(value) = state.players[index].ship.status else {
+            continue;
+        };
+        let position = state.players[index].ship.position;
+        if value.death_time == state.now {
+            // the wreck doesn't leave a trail behind
+            state
+                .particles
+                .retain(|particle| !matches!(particle.particle_type, ParticleType::Trail(_)));
+            state.play_positional(SoundEffect::Explosion, position);
+            add_shake(&mut state.shake, SHIP_SHAKE);
+            splat_dots(
+                position,
+                20,
+                value.impact,
+                &mut state.particles,
+                &mut state.random,
+            );
+            let ship = &state.players[index].ship;
+            spawn_debris(
+                &ship.hull(),
+                position,
+                ship.velocity,
+                &mut state.particles,
+                &mut state.random,
+            );
+            // neither the demo nor a zen run says anything about how well the player does
+            if !state.attract && !state.zen {
+                state.skill.life_lost();
+            }
+            state.run_log.log(state.now, state.wave, RunEvent::LifeLost);
+        }
+        // on the last life this is the game over
+        if state.now >= value.death_timer {
+            reset_level(state, index);
+        }
+    }
+
+    // Driven by time rather than frames so the tempo is the same at any refresh rate
+    let bloop_intensity = usize::min((state.now - state.stage_start).round() as usize / 15, 3);
+    let mut bloop_interval: f32 = 2.4;
+    for _ in 0..bloop_intensity {
+        bloop_interval /= 2.0;
+    }
+
+    // with nothing to lose in zen mode there's no tension to build
+    if state.now >= state.next_bloop && !state.zen {
+        state.bloop += 1;
+        state.next_bloop = state.now + bloop_interval;
+
+        if state
+            .players
+            .iter()
+            .any(|player| (&player.ship.status).into())
+        {
+            let sound = if state.bloop % 2 == 1 {
+                SoundEffect::BlopLow
+            } else {
+                SoundEffect::BlopHigh
+            };
+            state.play(sound);
+        }
+    }
+
+    // the sandbox stays empty until something is spawned
+    if !state.sandbox && state.aliens.len() == 0 && state.rocks.len() == 0 {
+        if !state.attract && !state.zen {
+            state.skill.wave_cleared(state.now - state.stage_start);
+        }
+        if state.stats.shots_fired == state.stage_shots {
+            state.stats.waves_without_firing += 1;
+        }
+        state.wave += 1;
+        reset_rocks(state);
+        // the demo has nothing to spend
+        if state.config.shop && !state.attract {
+            open_shop(state);
+        }
+    }
+
+    // nothing counts in the sandbox, which also keeps the score based spawns away
+    if state.sandbox {
+        state.score = 0;
+        state.last_score = 0;
+    }
+
+    // a spawn while the cap is reached is dropped, not saved up for later
+    let room_for_alien = |state: &State| state.aliens.len() < state.config.max_aliens;
+    if state.aliens_enabled
+        && room_for_alien(state)
+        && state.last_score / 5000 != state.score / 5000
+    {
+        let position = alien_spawn_position(state);
+        let event = RunEvent::AlienSpawned {
+            size: &AlienSize::Big,
+        };
+        state.run_log.log(state.now, state.wave, event);
+        state.aliens.push(Alien::new(position, AlienSize::Big));
+    }
+
+    if state.aliens_enabled
+        && room_for_alien(state)
+        && state.last_score / 8000 != state.score / 8000
+    {
+        let position = alien_spawn_position(state);
+        let event = RunEvent::AlienSpawned {
+            size: &AlienSize::Small,
+        };
+        state.run_log.log(state.now, state.wave, event);
+        state.aliens.push(Alien::new(position, AlienSize::Small));
+    }
+
+    if state.last_score / BOSS_SCORE != state.score / BOSS_SCORE {
+        spawn_boss(state);
+    }
+
+    // Only counts upwards so a reset of the score can't award a life
+    if state.run_mode == RunMode::Arcade
+        && state.score / EXTRA_LIFE_SCORE > state.last_score / EXTRA_LIFE_SCORE
+    {
+        let mut awarded = false;
+        if state.shares_lifes() {
+            if state.lifes < MAX_LIFES {
+                state.lifes += 1;
+                awarded = true;
+            }
+        } else {
+            for player in state.players.iter_mut().filter(|p| p.lifes < MAX_LIFES) {
+                player.lifes += 1;
+                awarded = true;
+            }
+        }
+        if awarded {
+            state.play(SoundEffect::ExtraLife);
+        }
+    }
+
+    update_survival_bonus(state);
+    update_achievements(state);
+
+    state.last_score = state.score;
+
+    state.combo.update(state.now);
+    update_shake(state);
+}
+
+/// Unlocks the achievements the run has reached and lines up their notifications.
+/// The demo and the sandbox don't count.
+fn update_achievements(state: &mut State) {
+    state
+        .toasts
+        .retain(|(_, shown)| shown + TOAST_TIME > state.now);
+    if state.attract || state.sandbox {
+        return;
+    }
+    let progress = Progress {
+        wave: state.wave,
+        rocks_destroyed: state.stats.total_rocks_destroyed(),
+        seconds: (state.now - state.run_start) as u32,
+        waves_without_firing: state.stats.waves_without_firing,
+    };
+    let unlocked = state.achievements.check(progress);
+    if unlocked.is_empty() {
+        return;
+    }
+    state.achievements.save();
+    for achievement in unlocked {
+        // one after the other when several unlock at once
+        let shown = state
+            .toasts
+            .last()
+            .map_or(state.now, |(_, shown)| (shown + TOAST_TIME).max(state.now));
+        state.toasts.push((achievement, shown));
+    }
+    state.play(SoundEffect::ExtraLife);
+}
+
+/// Steers, thrusts and fires the ship of player `index`
+fn update_player(state: &mut State, index: usize, input: &FrameInput) {
+    // the mouse is left to the first player, everyone else is on the keyboard
+    let scheme = if index == 0 {
+        state.control_scheme
+    } else {
+        ControlScheme::Keyboard
+    };
+    // taken out so the ship can change while the rest of the state is in use
+    let mut player = std::mem::take(&mut state.players[index]);
+    if (&player.ship.status).into() {
+        // rotations / second
+        const ROTATION_SPEED: f32 = 2.0;
+        const MOUSE_ROTATION_SPEED: f32 = 3.0;
+        const SHIP_SPEED: f32 = 24.0;
+
+        match scheme {
+            ControlScheme::Keyboard => {
+                let left = input.left;
+                let right = input.right;
+                let mut turn = 0.0;
+                if left {
+                    turn += 1.0;
+                }
+                if right {
+                    turn -= 1.0;
+                }
+                // the stick only steers while the keyboard doesn't, partial tilts turn slower
+                if !left && !right {
+                    turn = input.turn;
+                }
+
+                let target = std::f32::consts::TAU * ROTATION_SPEED * turn;
+                if state.config.turn_acceleration {
+                    player.ship.steer(target, state.delta);
+                } else {
+                    player.ship.angular_velocity = target;
+                }
+                player.ship.rotation += player.ship.angular_velocity * state.delta;
+            }
+            ControlScheme::Mouse | ControlScheme::TwinStick => {
+                // turning is done right here, nothing is left to carry on with the keys
+                player.ship.angular_velocity = 0.0;
+                let to_cursor = input.pointer - player.ship.position;
+                // With the cursor on top of the ship there is no direction to face
+                if to_cursor.length() > 1.0 {
+                    // undo the PI * 0.5 correction applied to get the ship direction
+                    let target = to_cursor.y.atan2(to_cursor.x) - std::f32::consts::PI * 0.5;
+                    let max_step = state.delta * std::f32::consts::TAU * MOUSE_ROTATION_SPEED;
+                    let difference = wrap_angle(target - player.ship.rotation);
+                    player.ship.rotation += difference.clamp(-max_step, max_step);
+                }
+            }
+        }
+
+        let corrected_ship_angle = player.ship.rotation + (std::f32::consts::PI * 0.5);
+        let ship_direction: Vec2 = Vec2::from_angle(corrected_ship_angle);
+
+        // neither thrust nor a dash pushes the ship past this, so it can't skip through
+        // rocks in one frame however little drag there is
+        const MAX_SHIP_SPEED: f32 = 20.0;
+
+        // twin-stick thrust goes along the screen's axes no matter where the ship faces,
+        // opposite keys cancel out
+        let thrust = if scheme == ControlScheme::TwinStick {
+            let axis = |negative: bool, positive: bool| match (negative, positive) {
+                (true, false) => -1.0,
+                (false, true) => 1.0,
+                _ => 0.0,
+            };
+            Vec2::new(
+                axis(input.left, input.right),
+                axis(input.thrust, input.reverse),
+            )
+            .try_normalize()
+        } else {
+            input.thrust.then_some(ship_direction)
+        };
+        if let Some(thrust_direction) = thrust {
+            let speed_limit = MAX_SHIP_SPEED.max(player.ship.velocity.length());
+            player.ship.velocity = (player.ship.velocity
+                + thrust_direction * state.delta * SHIP_SPEED)
+                .clamp_length_max(speed_limit);
+            player.thrust_direction = thrust_direction;
+            player.render_thruster_plume = (((state.now.round() as i32) * 10) % 2) == 0;
+            state.play(SoundEffect::Thruster);
+            // trail and exhaust from behind the thrust, the rear of the ship unless twin-stick
+            let rear = player.ship.position - thrust_direction * SCALE * 0.45;
+            spawn_trail(rear, player.ship.velocity.length(), &mut state.particles);
+            spawn_exhaust(
+                rear,
+                thrust_direction,
+                player.ship.velocity,
+                state.delta,
+                &mut state.particles,
+                &mut state.random,
+            );
+        } else {
+            player.render_thruster_plume = false;
+        }
+        if let Some(well) = state.gravity_well {
+            let wrap_mode = state.config.wrap_mode;
+            player.ship.velocity += well.pull(player.ship.position, wrap_mode) * state.delta;
+            if player.ship.is_vulnerable(state.now)
+                && well.swallows(player.ship.position, SCALE * 0.3, wrap_mode)
+            {
+                let volume = state.effective_volume();
+                let respawn_delay = state.respawn_delay();
+                // torn apart towards the middle of the well
+                let impact = playfield_delta(wrap_mode, player.ship.position, well.position);
+                hit_ship(
+                    &mut player.ship,
+                    state.now,
+                    HitEffects {
+                        particles: &mut state.particles,
+                        random: &mut state.random,
+                        audio: state.audio.as_ref(),
+                        volume,
+                    },
+                    respawn_delay,
+                    Some(impact),
+                );
+            }
+        }
+        // seconds between two dashes
+        const DASH_COOLDOWN: f32 = 2.0;
+        const DASH_IMPULSE: f32 = 12.0;
+        if input.dash && (state.now - player.ship.last_dash) > DASH_COOLDOWN {
+            player.ship.last_dash = state.now;
+            let speed_limit = MAX_SHIP_SPEED.max(player.ship.velocity.length());
+            player.ship.velocity = (player.ship.velocity + ship_direction * DASH_IMPULSE)
+                .clamp_length_max(speed_limit);
+            // a short trail along the way the ship is about to dash
+            for step in 0..4 {
+                let position = player.ship.position + player.ship.velocity * step as f32;
+                splat_dots(
+                    keep_in_frame(position),
+                    3,
+                    None,
+                    &mut state.particles,
+                    &mut state.random,
+                );
+            }
+            state.play(SoundEffect::Dash);
+        }
+        if input.bomb && player.bombs > 0 {
+            player.bombs -= 1;
+            detonate_bomb(state, &mut player.ship);
+        }
+
+        // precision flight brakes harder than the configured drag once the thrust lets go
+        let drag = state.flight_mode.drag(state.config.drag, thrust.is_some());
+        player.ship.velocity *= 1.0 - drag;
+        player.ship.position = player.ship.position + player.ship.velocity;
+        keep_in_arena(
+            state.config.wrap_mode,
+            &mut player.ship.position,
+            &mut player.ship.velocity,
+        );
+
+        // seconds between two shots
+        const FIRE_COOLDOWN: f32 = 0.15;
+        const MAX_PLAYER_PROJECTILES: usize = 4;
+        // rapid fire raises the cap instead of lifting it, a volley of spread shots
+        // every few frames would otherwise fill the screen
+        const RAPID_FIRE_COOLDOWN: f32 = 0.05;
+        const RAPID_FIRE_PROJECTILES: usize = 12;
+
+        let rapid_fire = state.now < state.rapid_fire_until;
+        let (cooldown, cap) = if rapid_fire {
+            (RAPID_FIRE_COOLDOWN, RAPID_FIRE_PROJECTILES)
+        } else {
+            (
+                FIRE_COOLDOWN * state.upgrades.cooldown_factor(),
+                MAX_PLAYER_PROJECTILES,
+            )
+        };
+
+        let player_projectiles = state
+            .projectiles
+            .iter()
+            .filter(|projectile| projectile.owner == Owner::Player(index) && projectile.is_alive())
+            .count();
+        // every projectile of a volley counts towards the cap
+        let angles = state.weapon.angles();
+        // taken before the recoil pushes the ship back
+        let inherited = if state.config.inherit_momentum {
+            player.ship.velocity
+        } else {
+            Vec2::ZERO
+        };
+        // rapid fire keeps shooting as long as the button is held, like auto fire does
+        let trigger = input.fire || ((state.config.auto_fire || rapid_fire) && input.fire_held);
+        if trigger
+            && (state.now - player.ship.last_shot) > cooldown
+            && player_projectiles + angles.len() <= cap
+        {
+            let position = player.ship.position + (ship_direction * (SCALE * 0.55));
+            for angle in angles {
+                let velocity = ship_direction.rotate(Vec2::from_angle(*angle))
+                    * state.config.shot_speed
+                    + inherited;
+                let projetile = Projectile {
+                    position,
+                    previous_position: position,
+                    velocity,
+                    state: ProjectileState::Alive {
+                        time_to_live: state.weapon.projectile_lifetime(state.config.shot_lifetime),
+                    },
+                    owner: Owner::Player(index),
+                    kind: state.weapon.projectile_kind(),
+                    pierce: 0,
+                    landed: false,
+                    kills: 0,
+                    kill_positions: Vec2::ZERO,
+                };
+                state.projectiles.push(projetile);
+                state.stats.shots_fired += 1;
+            }
+            // sound and recoil once per volley
+            player.ship.last_shot = state.now;
+            state.play(SoundEffect::Shoot);
+            let speed_limit = MAX_SHIP_SPEED.max(player.ship.velocity.length());
+            player.ship.velocity = (player.ship.velocity - ship_direction * state.config.recoil)
+                .clamp_length_max(speed_limit);
+        }
+
+        // Holding fire charges a piercing shot that is released with the button,
+        // a quick tap only fires the regular shot above
+        if input.fire_held && !state.config.auto_fire && !rapid_fire {
+            player.ship.charge += state.delta;
+        } else {
+            if player.ship.charge >= Ship::FULL_CHARGE {
+                const CHARGED_PIERCE: u32 = 3;
+                let position = player.ship.position + (ship_direction * (SCALE * 0.55));
+                state.projectiles.push(Projectile {
+                    position,
+                    previous_position: position,
+                    velocity: ship_direction * 14.0 + inherited,
+                    state: ProjectileState::Alive { time_to_live: 1.0 },
+                    owner: Owner::Player(index),
+                    kind: ProjectileKind::Charged,
+                    pierce: CHARGED_PIERCE,
+                    landed: false,
+                    kills: 0,
+                    kill_positions: Vec2::ZERO,
+                });
+                state.stats.shots_fired += 1;
+                player.ship.last_shot = state.now;
+                state.play(SoundEffect::Shoot);
+                // kicks three times as hard as a regular shot
+                let speed_limit = MAX_SHIP_SPEED.max(player.ship.velocity.length());
+                player.ship.velocity = (player.ship.velocity
+                    - ship_direction * state.config.recoil * 3.0)
+                    .clamp_length_max(speed_limit);
+            }
+            player.ship.charge = 0.0;
+        }
+    } else if let ShipStatus::Dead(death) = &mut player.ship.status {
+        // by then the burst of dots is gone, only the wreck's lines are still drifting
+        const SKIP_AFTER: f32 = 1.0;
+        if state.config.skip_respawn_delay
+            && input.fire
+            && state.now - death.death_time > SKIP_AFTER
+        {
+            death.death_timer = death.death_timer.min(state.now);
+        }
+    }
+    state.players[index] = player;
+}
+
+fn update_power_ups(state: &mut State) {
+    attract_collectibles(state);
+
+    let mut collected = vec![];
+    for power_up in state.power_ups.iter_mut() {
+        power_up.time_to_live -= state.delta;
+        if power_up.time_to_live <= 0.0 {
+            continue;
+        }
+        let picked_up_by = state.players.iter().position(|player| {
+            (&player.ship.status).into()
+                && playfield_delta(
+                    state.config.wrap_mode,
+                    power_up.position,
+                    player.ship.position,
+                )
+                .length()
+                    < power_up.collision_size()
+        });
+        if let Some(index) = picked_up_by {
+            power_up.time_to_live = 0.0;
+            collected.push((power_up.kind, index));
+        }
+    }
+    state
+        .power_ups
+        .retain(|power_up| power_up.time_to_live > 0.0);
+
+    // bullet time and weapons are for everyone, shields and bombs only for whoever picked them up
+    for (kind, index) in collected {
+        match kind {
+            PowerUpKind::BulletTime => state.time_scale = BULLET_TIME_SCALE,
+            PowerUpKind::SpreadShot => {
+                state.weapon = Weapon::Spread;
+                state.weapon_expires = state.now + WEAPON_DURATION;
+            }
+            PowerUpKind::Ricochet => {
+                state.weapon = Weapon::Ricochet;
+                state.weapon_expires = state.now + WEAPON_DURATION;
+            }
+            PowerUpKind::RapidFire => state.rapid_fire_until = state.now + RAPID_FIRE_DURATION,
+            PowerUpKind::Magnet => state.magnet_until = state.now + MAGNET_DURATION,
+            PowerUpKind::Ore => state.credits += 1,
+            PowerUpKind::Shield => state.players[index].ship.shielded = true,
+            PowerUpKind::Bomb => {
+                let player = &mut state.players[index];
+                player.bombs = (player.bombs + 1).min(Player::MAX_BOMBS);
+            }
+        }
+        state.play(SoundEffect::BlopHigh);
+    }
+
+    if state.weapon != Weapon::Single && state.now > state.weapon_expires {
+        state.weapon = Weapon::Single;
+    }
+
+    // Runs on real time, the bloop and the death timer never see the time scale
+    state.time_scale = (state.time_scale + state.delta / BULLET_TIME_RECOVERY).min(1.0);
+}
+
+/// While a magnet is active, power-ups and score popups within reach of a ship home in
+/// on it. A magnet lying around pulls itself in from close by, so it is easy to pick up.
+/// Rocks and shots are never touched.
+fn attract_collectibles(state: &mut State) {
+    // how far the pull reaches, the magnet's own reach while it waits to be collected
+    const MAGNET_RADIUS: f32 = SCALE * 12.0;
+    const SELF_PULL_RADIUS: f32 = SCALE * 3.0;
+    // pixels per frame the collectibles end up flying at
+    const MAGNET_SPEED: f32 = 8.0;
+    // share of the way to that velocity covered each frame, for a smooth turn
+    const STEER: f32 = 0.1;
+
+    let magnet = state.now < state.magnet_until;
+    let wrap_mode = state.config.wrap_mode;
+    let steer = |position: Vec2, velocity: Vec2, radius: f32| {
+        let to_ship = nearest_ship(&state.players, position, wrap_mode)
+            .map(|ship| playfield_delta(wrap_mode, position, ship))
+            .filter(|to_ship| to_ship.length() < radius)?;
+        let desired = to_ship.normalize_or_zero() * MAGNET_SPEED;
+        Some(velocity + (desired - velocity) * STEER * state.time_scale)
+    };
+
+    for power_up in state.power_ups.iter_mut() {
+        let radius = if magnet {
+            MAGNET_RADIUS
+        } else if power_up.kind == PowerUpKind::Magnet {
+            SELF_PULL_RADIUS
+        } else {
+            0.0
+        };
+        if let Some(velocity) = steer(power_up.position, power_up.velocity, radius) {
+            power_up.velocity = velocity;
+        }
+        power_up.position += power_up.velocity * state.time_scale;
+        keep_in_arena(wrap_mode, &mut power_up.position, &mut power_up.velocity);
+    }
+
+    if magnet {
+        for particle in state.particles.iter_mut() {
+            if !matches!(particle.particle_type, ParticleType::Text(_)) {
+                continue;
+            }
+            if let Some(velocity) = steer(particle.position, particle.velocity, MAGNET_RADIUS) {
+                particle.velocity = velocity;
+            }
+        }
+    }
+}
+
+/// Slows the world down while a ship on its last life is about to run into something.
+/// Blends in and out over a moment and, like bullet time, only touches `time_scale`.
+fn update_last_stand(state: &mut State) {
+    // world speed at the height of it
+    const SCALE_AT_FULL: f32 = 0.6;
+    // seconds to blend fully in or out
+    const BLEND: f32 = 0.4;
+    // distance between the outlines that counts as close
+    const DANGER_DISTANCE: f32 = SCALE * 2.5;
+
+    let wrap_mode = state.config.wrap_mode;
+    let endangered = |index: usize, ship: &Ship| {
+        let near = |position: Vec2, radius: f32| {
+            playfield_delta(wrap_mode, ship.position, position).length() - radius < DANGER_DISTANCE
+        };
+        ship.is_vulnerable(state.now)
+            && state.lifes(index) == 0
+            && (state
+                .rocks
+                .iter()
+                .any(|rock| near(rock.position, rock.radius()))
+                || state
+                    .aliens
+                    .iter()
+                    .any(|alien| near(alien.position, alien.radius())))
+    };
+    // nothing is at stake without a game over
+    let active = state.config.last_stand
+        && state.run_mode == RunMode::Arcade
+        && !state.zen
+        && !state.sandbox
+        && state
+            .players
+            .iter()
+            .enumerate()
+            .any(|(index, player)| endangered(index, &player.ship));
+
+    let target = if active { 1.0 } else { 0.0 };
+    let step = state.delta / BLEND;
+    state.last_stand += (target - state.last_stand).clamp(-step, step);
+    let slowed = 1.0 - (1.0 - SCALE_AT_FULL) * state.last_stand;
+    state.time_scale = state.time_scale.min(slowed);
+}
+
+/// Every few seconds a ship stays alive are worth some points
+fn update_survival_bonus(state: &mut State) {
+    const BONUS_INTERVAL: f32 = 10.0;
+    const SURVIVAL_BONUS: usize = 50;
+
+    if state.sandbox {
+        return;
+    }
+    for player in state.players.iter() {
+        if !bool::from(&player.ship.status) {
+            continue;
+        }
+        let alive = state.now - player.life_start;
+        let intervals = (alive / BONUS_INTERVAL) as usize;
+        let last_intervals = ((alive - state.delta) / BONUS_INTERVAL).max(0.0) as usize;
+        if intervals > last_intervals {
+            state.score += SURVIVAL_BONUS;
+            spawn_score_popup(player.ship.position, SURVIVAL_BONUS, &mut state.particles);
+        }
+    }
+}
+
+/// How much faster than their base timings aliens shoot and turn, grows with the time
+/// survived and the wave and is capped so the aliens never fire nonstop
+pub(crate) fn alien_aggression(state: &State) -> f32 {
+    // seconds until the time alone doubles the pace
+    const DOUBLING_TIME: f32 = 300.0;
+    const PER_WAVE: f32 = 0.1;
+    const MAX_AGGRESSION: f32 = 2.0;
+
+    let survived = state.now - state.run_start;
+    let aggression = 1.0 + survived / DOUBLING_TIME + (state.wave - 1) as f32 * PER_WAVE;
+    aggression.min(MAX_AGGRESSION)
+}
+
+/// Position of the living ship closest to `position`, over the edges if the playfield wraps
+fn nearest_ship(players: &[Player], position: Vec2, wrap_mode: WrapMode) -> Option<Vec2> {
+    players
+        .iter()
+        .filter(|player| (&player.ship.status).into())
+        .map(|player| player.ship.position)
+        .min_by(|a, b| {
+            let a = playfield_delta(wrap_mode, position, *a).length();
+            let b = playfield_delta(wrap_mode, position, *b).length();
+            a.total_cmp(&b)
+        })
+}
+
+/// Spawns at the cursor: 1 to 3 rocks from big to small, 4 a boss,
+/// 5 and 6 a big and a small alien. Backspace clears the field.
+fn update_sandbox(state: &mut State) {
+    let cursor = mouse_playfield_position(state.config.render_scale);
+    let rock_size = if is_key_pressed(KeyCode::Key1) {
+        Some(RockSize::Big)
+    } else if is_key_pressed(KeyCode::Key2) {
+        Some(RockSize::Medium)
+    } else if is_key_pressed(KeyCode::Key3) {
+        Some(RockSize::Small)
+    } else if is_key_pressed(KeyCode::Key4) {
+        Some(RockSize::Boss)
+    } else {
+        None
+    };
+    if let Some(size) = rock_size {
+        let angle = std::f32::consts::TAU * state.random.gen::<f32>();
+        let health = if size == RockSize::Boss {
+            BOSS_HEALTH
+        } else {
+            1
+        };
+        state.rocks.push(Rock {
+            position: cursor,
+            velocity: Vec2::from_angle(angle) * size.get_velocity(),
+            seed: state.random.gen::<u64>(),
+            health,
+            angular_velocity: random_spin(&size, &mut state.random),
+            size,
+            ..Default::default()
+        });
+    }
+
+    if is_key_pressed(KeyCode::Key5) {
+        state.aliens.push(Alien::new(cursor, AlienSize::Big));
+    }
+    if is_key_pressed(KeyCode::Key6) {
+        state.aliens.push(Alien::new(cursor, AlienSize::Small));
+    }
+
+    if is_key_pressed(KeyCode::Backspace) {
+        state.rocks.clear();
+        state.aliens.clear();
+        state.projectiles.clear();
+        state.power_ups.clear();
+    }
+}
+
+fn update_fullscreen(state: &mut State) {
+    // ignore key repeats or bouncing switches flipping the mode again right away
+    const DEBOUNCE: f32 = 0.3;
+
+    if is_key_pressed(KeyCode::F11) && (state.now - state.last_fullscreen_toggle) > DEBOUNCE {
+        state.last_fullscreen_toggle = state.now;
+        state.fullscreen = !state.fullscreen;
+        set_fullscreen(state.fullscreen);
+        if !state.fullscreen {
+            request_new_screen_size(SIZE.x, SIZE.y);
+        }
+    }
+}
+
+/// Pushes rocks away from explosions, the closer they are the harder.
+/// The ship is deliberately left alone.
+fn apply_shockwaves(rocks: &mut [Rock], shockwaves: &[(Vec2, f32)], wrap_mode: WrapMode) {
+    for &(origin, strength) in shockwaves {
+        for rock in rocks.iter_mut().filter(|rock| !rock.removed) {
+            let delta = playfield_delta(wrap_mode, origin, rock.position);
+            let distance = delta.length();
+            if distance >= SHOCKWAVE_RADIUS {
+                continue;
+            }
+            // fragments spawned right at the origin have no direction to be pushed in
+            let Some(direction) = delta.try_normalize() else {
+                continue;
+            };
+            let falloff = 1.0 - distance / SHOCKWAVE_RADIUS;
+            rock.velocity =
+                (rock.velocity + direction * strength * falloff).clamp_length_max(MAX_ROCK_SPEED);
+        }
+    }
+}
+
+/// Breaks every rock around the ship as if it had been shot, wipes out incoming fire and
+/// pushes whatever is left away. The ship is briefly invulnerable so the fragments flying
+/// off can't take it out right away.
+fn detonate_bomb(state: &mut State, ship: &mut Ship) {
+    const BOMB_RADIUS: f32 = SCALE * 8.0;
+    const BOMB_GRACE: f32 = 1.5;
+    const BOMB_SHOCKWAVE: f32 = 5.0;
+
+    let wrap_mode = state.config.wrap_mode;
+    let volume = state.effective_volume();
+    let center = ship.position;
+    let in_range =
+        |position: Vec2| playfield_delta(wrap_mode, center, position).length() < BOMB_RADIUS;
+
+    let mut fragments = vec![];
+    for rock in state.rocks.iter_mut() {
+        if rock.removed || !in_range(rock.position) {
+            continue;
+        }
+        // armored rocks break in one go, a boss only takes a single hit
+        if rock.size != RockSize::Boss {
+            rock.health = 1;
+        }
+        let away = playfield_delta(wrap_mode, center, rock.position).try_normalize();
+        let new_rocks = hit_rock(
+            rock,
+            &mut state.random,
+            &mut state.particles,
+            away,
+            state.audio.as_ref(),
+            volume,
+            &mut state.shake,
+        );
+        if rock.removed {
+            let points = rock.size.get_score() * state.combo.register(state.now);
+            state.score += points;
+            spawn_score_popup(rock.position, points, &mut state.particles);
+            state.stats.rock_destroyed(&rock.size);
+            let size = &rock.size;
+            let event = RunEvent::RockDestroyed { size, points };
+            state.run_log.log(state.now, state.wave, event);
+        }
+        if let Some(mut new_rocks) = new_rocks {
+            fragments.append(&mut new_rocks);
+        }
+    }
+    apply_shockwaves(&mut fragments, &[(center, BOMB_SHOCKWAVE)], wrap_mode);
+    cap_fragments(state, &mut fragments);
+    grant_fragment_grace(&mut fragments, state.now);
+    state.rocks.append(&mut fragments);
+
+    for projectile in state.projectiles.iter_mut() {
+        if projectile.owner == Owner::Alien && in_range(projectile.position) {
+            projectile.state = ProjectileState::Dead;
+        }
+    }
+
+    ship.invulnerable_until = ship.invulnerable_until.max(state.now + BOMB_GRACE);
+    state.play(SoundEffect::Bomb);
+    add_shake(&mut state.shake, MAX_SHAKE);
+    splat_dots(center, 80, None, &mut state.particles, &mut state.random);
+    splat_lines(center, 12, None, &mut state.particles, &mut state.random);
+}
+
+/// The parts of the `State` a hit shows and plays its effects with, borrowed apart from
+/// the ship that was hit
+struct HitEffects<'a> {
+    particles: &'a mut ParticlePool,
+    random: &'a mut Xoshiro256PlusPlus,
+    audio: &'a dyn AudioSink,
+    volume: f32,
+}
+
+/// Destroys the ship unless its shield absorbs the hit, which leaves it briefly invulnerable
+/// so whatever broke the shield can't kill it in the very next frame. A hyperspace misjump,
+/// should one be added, isn't a hit and is meant to bypass the shield.
+fn hit_ship(
+    ship: &mut Ship,
+    now: f32,
+    effects: HitEffects,
+    respawn_delay: Option<f32>,
+    impact: Option<Vec2>,
+) {
+    const SHIELD_GRACE: f32 = 1.0;
+
+    if ship.shielded {
+        ship.shielded = false;
+        ship.invulnerable_until = now + SHIELD_GRACE;
+        splat_dots(ship.position, 20, impact, effects.particles, effects.random);
+        effects.audio.play(SoundEffect::Shield, effects.volume);
+    } else {
+        kill_ship(ship, now, respawn_delay, impact);
+    }
+}
+
+/// Every ship that is destroyed ends up here. Without a `respawn_delay`, in zen mode,
+/// it isn't and only passes through whatever hit it for a moment.
+fn kill_ship(ship: &mut Ship, now: f32, respawn_delay: Option<f32>, impact: Option<Vec2>) {
+    const ZEN_GRACE: f32 = 1.0;
+
+    match respawn_delay {
+        Some(delay) => {
+            ship.status = ShipStatus::Dead(DeathTime {
+                impact,
+                ..DeathTime::new(now, delay)
+            })
+        }
+        None => ship.invulnerable_until = now + ZEN_GRACE,
+    }
+}
+
+fn add_shake(shake: &mut f32, amount: f32) {
+    *shake = (*shake + amount).min(MAX_SHAKE);
+}
+
+fn update_shake(state: &mut State) {
+    // exponential decay, independent of the frame rate
+    const SHAKE_DECAY: f32 = 6.0;
+
+    state.shake *= (-SHAKE_DECAY * state.delta).exp();
+    if state.shake < 0.05 {
+        state.shake = 0.0;
+    }
+
+    let angle = std::f32::consts::TAU * state.random.gen::<f32>();
+    state.shake_offset = Vec2::from_angle(angle) * state.shake * state.random.gen::<f32>();
+}
+
+fn hit_rock(
+    rock: &mut Rock,
+    random: &mut Xoshiro256PlusPlus,
+    particles: &mut ParticlePool,
+    impact: Option<Vec2>,
+    audio: &dyn AudioSink,
+    volume: f32,
+    shake: &mut f32,
+) -> Option<Vec<Rock>> {
+    audio.play(SoundEffect::Asteroid, volume);
+    splat_dots(rock.position, 10, impact, particles, random);
+
+    // Armored rocks soak up hits before they break
+    rock.health = rock.health.saturating_sub(1);
+    if rock.health > 0 {
+        return Option::None;
+    }
+
+    rock.removed = true;
+    add_shake(shake, rock.size.get_shake());
+
+    if let RockSize::Small = rock.size {
+        return Option::None;
+    }
+
+    if let RockSize::Boss = rock.size {
+        // bursts into a ring of medium rocks flying outwards
+        const RING: usize = 8;
+        let offset = std::f32::consts::TAU * random.gen::<f32>();
+        let ring = (0..RING)
+            .map(|i| {
+                let angle = offset + i as f32 * std::f32::consts::TAU / RING as f32;
+                let direction = Vec2::from_angle(angle);
+                Rock {
+                    position: rock.position + direction * rock.size.get_size() * 0.25,
+                    velocity: direction * 2.0 * RockSize::Medium.get_velocity(),
+                    size: RockSize::Medium,
+                    seed: random.gen::<u64>(),
+                    angular_velocity: random_spin(&RockSize::Medium, random),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        return Some(ring);
+    }
+
+    // the fragments keep the parent's heading, a resting parent goes along with the hit
+    let forward = rock
+        .velocity
+        .try_normalize()
+        .or(impact)
+        .unwrap_or_else(|| Vec2::from_angle(std::f32::consts::TAU * random.gen::<f32>()));
+    // and fly apart across the hit, one to each side
+    let across = impact.unwrap_or(forward).perp();
+    let impact = impact.map_or(Vec2::ZERO, |imp| imp * 1.5);
+    let mut new_rocks = vec![];
+    for side in [1.0, -1.0] {
+        let spread = side * (0.5 + 0.7 * random.gen::<f32>());
+        let new_direction = (forward + across * spread).normalize();
+        let speed = 1.5 * (0.4 + 0.6 * random.gen::<f32>());
+        let new_size = match rock.size {
+            RockSize::Big => RockSize::Medium,
+            RockSize::Medium => RockSize::Small,
+            RockSize::Boss | RockSize::Small => unreachable!(),
+        };
+        let new_rock = Rock {
+            position: rock.position,
+            velocity: (new_direction * speed * rock.size.get_velocity()) + impact,
+            seed: random.gen::<u64>(),
+            angular_velocity: random_spin(&new_size, random),
+            size: new_size,
+            ..Default::default()
+        };
+        new_rocks.push(new_rock);
+    }
+    Some(new_rocks)
+}
+
+fn keep_in_frame(vec: Vec2) -> Vec2 {
+    let new_x = if vec.x <= 0.0 { SIZE.x } else { vec.x % SIZE.x };
+    let new_y = if vec.y <= 0.0 { SIZE.y } else { vec.y % SIZE.y };
+    // debug!("x:{}, y:{}", new_x, new_y);
+    Vec2::new(new_x, new_y)
+}
+
+/// Keeps something moving along `velocity` on the playfield, either by wrapping it
+/// around to the other side or by bouncing it off the walls of the arena
+fn keep_in_arena(wrap_mode: WrapMode, position: &mut Vec2, velocity: &mut Vec2) {
+    match wrap_mode {
+        WrapMode::Wrap => *position = keep_in_frame(*position),
+        WrapMode::Bounce => {
+            bounce_off_edges(position, velocity);
+        }
+    }
+}
+
+/// Mirrors `velocity` off the edges of the playfield `position` went past
+/// and puts it back inside. Returns whether it bounced.
+fn bounce_off_edges(position: &mut Vec2, velocity: &mut Vec2) -> bool {
+    let mut bounced = false;
+    if position.x < 0.0 || position.x > SIZE.x {
+        velocity.x = -velocity.x;
+        position.x = position.x.clamp(0.0, SIZE.x);
+        bounced = true;
+    }
+    if position.y < 0.0 || position.y > SIZE.y {
+        velocity.y = -velocity.y;
+        position.y = position.y.clamp(0.0, SIZE.y);
+        bounced = true;
+    }
+    bounced
+}
+
+/// Vector from `from` to `to`, the shortest way over the edges only if the playfield wraps
+pub(crate) fn playfield_delta(wrap_mode: WrapMode, from: Vec2, to: Vec2) -> Vec2 {
+    match wrap_mode {
+        WrapMode::Wrap => wrapped_delta(from, to),
+        WrapMode::Bounce => to - from,
+    }
+}
+
+/// Shortest vector pointing from `from` to `to` on the wrapping playfield
+pub(crate) fn wrapped_delta(from: Vec2, to: Vec2) -> Vec2 {
+    let mut delta = to - from;
+    if delta.x > SIZE.x * 0.5 {
+        delta.x -= SIZE.x;
+    } else if delta.x < -SIZE.x * 0.5 {
+        delta.x += SIZE.x;
+    }
+    if delta.y > SIZE.y * 0.5 {
+        delta.y -= SIZE.y;
+    } else if delta.y < -SIZE.y * 0.5 {
+        delta.y += SIZE.y;
+    }
+    delta
+}
+
+/// Wraps an angle into the range -PI..=PI
+pub(crate) fn wrap_angle(angle: f32) -> f32 {
+    let wrapped = (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU);
+    wrapped - std::f32::consts::PI
+}
+
+fn reset_rocks(state: &mut State) {
+    if !state.rocks.is_empty() {
+        state.rocks.clear();
+    }
+
+    // keeps away from the middle where the ship respawns
+    state.gravity_well = (state.config.gravity_wells && state.wave >= GravityWell::FIRST_WAVE)
+        .then(|| {
+            let angle = std::f32::consts::TAU * state.random.gen::<f32>();
+            let distance = SCALE * (6.0 + 4.0 * state.random.gen::<f32>());
+            GravityWell::new(SIZE * 0.5 + Vec2::from_angle(angle) * distance)
+        });
+
+    // a density picked mid-wave only applies from here on
+    state.field_density = state.density;
+    let density = state.field_density.multiplier();
+    // every wave brings more and faster rocks, the score still adds a few on top
+    let skill = if state.config.adaptive_difficulty {
+        state.skill.level
+    } else {
+        1.0
+    };
+    let bound = ((16 + state.wave * 4 + state.score / 3000) as f32 * density * skill) as usize;
+    let bound = bound.min(state.config.max_rocks);
+    let speed = (1.0 + 0.1 * (state.wave - 1) as f32).min(2.0);
+    // more armored rocks the higher the score gets
+    const ARMORED_HEALTH: u32 = 3;
+    let armored_chance = (state.score as f32 / 50000.0).min(0.3) * density;
+
+    let pattern = SpawnPattern::for_wave(state.wave, &mut state.random);
+    for _ in 0..bound {
+        let angle = std::f32::consts::TAU * state.random.gen::<f32>();
+        let rock_size: RockSize = state.random.gen::<f32>().into();
+        let position = rock_spawn_position(state, &pattern);
+        let direction = pattern.direction(position, angle);
+        let rock = Rock {
+            position,
+            velocity: direction
+                * 3.0
+                * speed
+                * state.random.gen::<f32>()
+                * rock_size.get_velocity(),
+            seed: state.random.gen::<u64>(),
+            angular_velocity: random_spin(&rock_size, &mut state.random),
+            size: rock_size,
+            health: if state.random.gen::<f32>() < armored_chance {
+                ARMORED_HEALTH
+            } else {
+                1
+            },
+            // grows in first, the wave banner and the beat still start right away
+            spawned: state.now,
+            harmless_until: state.now + Rock::INTRO,
+            ..Default::default()
+        };
+        state.rocks.push(rock);
+    }
+
+    state.stage_start = state.now;
+    state.stage_shots = state.stats.shots_fired;
+    let rocks = state.rocks.len();
+    state
+        .run_log
+        .log(state.now, state.wave, RunEvent::WaveStarted { rocks });
+}
+
+/// A random spot for a rock of a new wave following `pattern`, away from the ships if
+/// the config asks for it. Should every try land too close, the one furthest from them
+/// is taken.
+fn rock_spawn_position(state: &mut State, pattern: &SpawnPattern) -> Vec2 {
+    const TRIES: usize = 16;
+
+    let tries = if state.config.spawn_away_from_ship {
+        TRIES
+    } else {
+        1
+    };
+    // well beyond the reach of the largest rock, there's time to react to a boss as well
+    let safe_radius = collision_cell_size() + SCALE * 3.0;
+    let wrap_mode = state.config.wrap_mode;
+    let ships: Vec<Vec2> = state
+        .players
+        .iter()
+        .map(|player| player.ship.position)
+        .collect();
+
+    let mut best = (Vec2::ZERO, f32::MIN);
+    for _ in 0..tries {
+        let spot = pattern.position(&mut state.random, state.config.spawn_margin);
+        let distance = ships
+            .iter()
+            .map(|&ship| playfield_delta(wrap_mode, ship, spot).length())
+            .fold(f32::MAX, f32::min);
+        if distance >= safe_radius {
+            return spot;
+        }
+        if distance > best.1 {
+            best = (spot, distance);
+        }
+    }
+    best.0
+}
+
+/// Aliens come in at the left or right edge, the spawn margin still applies
+fn alien_spawn_position(state: &mut State) -> Vec2 {
+    let margin = state.config.spawn_margin;
+    let x = if state.random.gen::<bool>() {
+        margin
+    } else {
+        SIZE.x - SCALE - margin
+    };
+    let y = margin + state.random.gen::<f32>() * (SIZE.y - margin * 2.0);
+    Vec2::new(x, y)
+}
+
+fn spawn_boss(state: &mut State) {
+    // enters from a random edge, slowly drifting across the playfield
+    let position = if state.random.gen::<bool>() {
+        Vec2::new(0.0, state.random.gen::<f32>() * SIZE.y)
+    } else {
+        Vec2::new(state.random.gen::<f32>() * SIZE.x, 0.0)
+    };
+    let angle = std::f32::consts::TAU * state.random.gen::<f32>();
+    state.rocks.push(Rock {
+        position,
+        velocity: Vec2::from_angle(angle) * RockSize::Boss.get_velocity(),
+        size: RockSize::Boss,
+        seed: state.random.gen::<u64>(),
+        health: BOSS_HEALTH,
+        angular_velocity: random_spin(&RockSize::Boss, &mut state.random),
+        ..Default::default()
+    });
+}
+
+/// Spin for a new rock, either way round and at most as fast as its size allows
+fn random_spin(size: &RockSize, random: &mut Xoshiro256PlusPlus) -> f32 {
+    (random.gen::<f32>() * 2.0 - 1.0) * size.get_spin()
+}
+
+/// Brings back the ship of player `index`. Without lives left it stays dead,
+/// the game is only over once no ship is flying or able to come back.
+fn reset_level(state: &mut State, index: usize) {
+    // score an endless run loses with every ship
+    const ENDLESS_PENALTY: usize = 5000;
+    // seconds a ship is shielded after coming back in an endless run
+    const ENDLESS_GRACE: f32 = 3.0;
+
+    let ship_alive: bool = (&state.players[index].ship.status).into();
+    // lives are endless in the sandbox
+    let lost = !ship_alive && !state.sandbox;
+    if lost {
+        match state.run_mode {
+            RunMode::Endless => {
+                state.score = state.score.saturating_sub(ENDLESS_PENALTY);
+                // the lost points don't count as crossing the thresholds that spawn aliens
+                state.last_score = state.score;
+                let position = state.players[index].ship.position;
+                spawn_text_popup(
+                    position,
+                    format!("-{}", ENDLESS_PENALTY),
+                    &mut state.particles,
+                );
+            }
+            RunMode::Arcade if state.lifes(index) == 0 => {
+                let anyone_left = (0..state.players.len()).any(|other| {
+                    (&state.players[other].ship.status).into() || state.lifes(other) > 0
+                });
+                if !anyone_left {
+                    game_over(state);
+                }
+                return;
+            }
+            RunMode::Arcade => *state.lifes_mut(index) -= 1,
+        }
+    }
+    let count = state.players.len();
+    state.players[index].respawn(index, count, state.now);
+    if lost && state.run_mode == RunMode::Endless {
+        state.players[index].ship.invulnerable_until = state.now + ENDLESS_GRACE;
+    }
+    state.power_ups.clear();
+    state.time_scale = 1.0;
+    state.weapon = Weapon::Single;
+}
+
+/// Freezes the run behind the game over screen, the dead ship stays until `reset_game`
+fn game_over(state: &mut State) {
+    const CONTINUE_TIME: f32 = 10.0;
+
+    state.game_state = GameState::GameOver;
+    // a continued daily challenge wouldn't compare to anyone else's
+    state.continue_left = if state.daily.is_some() {
+        0.0
+    } else {
+        CONTINUE_TIME
+    };
+    stop_all_sounds(state);
+    // the demo doesn't get to beat anyone
+    if state.attract {
+        return;
+    }
+    record_high_score(state);
+}
+
+/// Keeps the score if it beats the best one of the current run mode. Every run that
+/// counts ends here, so it's also where it ends in the run log.
+fn record_high_score(state: &mut State) {
+    let score = state.score;
+    state
+        .run_log
+        .log(state.now, state.wave, RunEvent::RunEnded { score });
+    state.new_high_score = state.score > state.best_score();
+    if !state.new_high_score {
+        return;
+    }
+    match (state.daily, state.run_mode) {
+        (Some(_), _) => state.daily_high_score = state.score,
+        (None, RunMode::Arcade) => state.high_score = state.score,
+        (None, RunMode::Endless) => state.endless_high_score = state.score,
+    }
+    let stored = &mut state.high_scores;
+    stored.score = state.high_score;
+    stored.endless = state.endless_high_score;
+    if let Some(day) = state.daily {
+        stored
+            .daily
+            .insert(daily::date(day), state.daily_high_score);
+    }
+    stored.save();
+}
+
+/// Picks the run up again where it ended, with the score and wave kept but the lives
+/// back at the start. The rocks stay, so the new ships are shielded for a moment.
+fn continue_game(state: &mut State) {
+    const CONTINUE_GRACE: f32 = 3.0;
+
+    state.lifes = state.start_lifes;
+    state.continue_left = 0.0;
+    state.new_high_score = false;
+    let count = state.player_count();
+    state.players = (0..count)
+        .map(|index| Player::new(index, count, state.start_lifes, state.now))
+        .collect();
+    for index in 0..count {
+        reset_level(state, index);
+        state.players[index].ship.invulnerable_until = state.now + CONTINUE_GRACE;
+    }
+    state.projectiles.clear();
+}
+
+pub(crate) fn reset_game(state: &mut State) {
+    state.lifes = state.start_lifes;
+    state.score = 0;
+    // the score thresholds compare against it, a stale one would spawn aliens right away
+    state.last_score = 0;
+    state.combo = Combo::default();
+    state.stats = RunStats::default();
+    state.credits = 0;
+    state.upgrades = Upgrades::default();
+    state.skill.new_run();
+    state.bloop = 0;
+    state.next_bloop = state.now;
+    state.aliens.clear();
+    stop_all_sounds(state);
+    state.projectiles.clear();
+    // power-ups, the slow motion and the shake belong to the run that just ended
+    state.weapon = Weapon::Single;
+    state.weapon_expires = 0.0;
+    state.rapid_fire_until = 0.0;
+    state.magnet_until = 0.0;
+    state.last_stand = 0.0;
+    state.time_scale = 1.0;
+    state.shake = 0.0;
+    state.shake_offset = Vec2::ZERO;
+    // fresh ships, otherwise the ones that just died cost a life right away
+    let count = state.player_count();
+    state.players = (0..count)
+        .map(|index| Player::new(index, count, state.start_lifes, state.now))
+        .collect();
+    state.wave = 1;
+    state.run_start = state.now;
+
+    for index in 0..count {
+        reset_level(state, index);
+    }
+    reset_rocks(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        input::{InputProvider, ScriptedInput},
+        sound::SoundLog,
+    };
+
+    fn headless_state(audio: SoundLog) -> State {
+        let mut state = State::new(
+            Box::new(audio),
+            Settings::default(),
+            Config::default(),
+            Some(820),
+        );
+        reset_game(&mut state);
+        state.game_state = GameState::Playing;
+        state
+    }
+
+    /// One frame the way the main loop runs it
+    fn step(state: &mut State, input: FrameInput) {
+        step_for(state, 1.0 / 60.0, input);
+    }
+
+    fn step_for(state: &mut State, delta: f32, input: FrameInput) {
+        advance_clock(state, delta);
+        update(state, &[input]);
+        state.frame += 1;
+    }
+
+    /// Steps until the provider runs out, like the main loop does with a replay
+    fn play(state: &mut State, provider: &mut dyn InputProvider) {
+        while let Some(input) = provider.next_step(state) {
+            advance_clock(state, input.delta);
+            update(state, &input.inputs);
+            state.frame += 1;
+        }
+    }
+
+    #[test]
+    fn a_scripted_thrust_moves_the_ship_forwards() {
+        let mut state = headless_state(SoundLog::default());
+        let start = state.ship().position;
+        let thrust = FrameInput {
+            thrust: true,
+            ..Default::default()
+        };
+        let mut script = ScriptedInput::new(vec![thrust; 30]);
+
+        play(&mut state, &mut script);
+
+        let ship = state.ship();
+        let forward = Vec2::from_angle(ship.rotation + std::f32::consts::FRAC_PI_2);
+        assert!(state.frame >= 30);
+        assert!((ship.position - start).dot(forward) > 0.0);
+    }
+
+    #[test]
+    fn a_loaded_run_plays_out_like_the_original() {
+        let input = |frame: usize| FrameInput {
+            left: frame % 50 < 20,
+            thrust: frame % 30 < 10,
+            fire: frame % 7 == 3,
+            ..Default::default()
+        };
+        let mut original = headless_state(SoundLog::default());
+        for frame in 0..120 {
+            step(&mut original, input(frame));
+        }
+
+        let saved = serde_json::to_string(&original).unwrap();
+        let mut resumed = headless_state(SoundLog::default());
+        save::restore(&mut resumed, serde_json::from_str(&saved).unwrap()).unwrap();
+        for frame in 120..600 {
+            step(&mut original, input(frame));
+            step(&mut resumed, input(frame));
+        }
+
+        assert_eq!(
+            serde_json::to_string(&original).unwrap(),
+            serde_json::to_string(&resumed).unwrap()
+        );
+    }
+
+    #[test]
+    fn a_save_only_loads_into_the_daily_challenge_it_was_made_in() {
+        let mut daily = headless_state(SoundLog::default());
+        daily.daily = Some(20000);
+        let mut state = headless_state(SoundLog::default());
+        assert!(save::restore(&mut state, daily).is_err());
+        assert!(state.daily.is_none());
+
+        let mut state = headless_state(SoundLog::default());
+        state.daily = Some(20000);
+        assert!(save::restore(&mut state, headless_state(SoundLog::default())).is_err());
+        assert!(state.daily == Some(20000));
+    }
+
+    #[test]
+    fn the_same_seed_and_input_lay_out_the_same_rocks() {
+        let layout = |state: &State| -> Vec<(Vec2, u64)> {
+            state
+                .rocks
+                .iter()
+                .map(|rock| (rock.position, rock.seed))
+                .collect()
+        };
+        let thrust = FrameInput {
+            thrust: true,
+            left: true,
+            ..Default::default()
+        };
+        let mut first = headless_state(SoundLog::default());
+        let mut second = headless_state(SoundLog::default());
+        // a state that got somewhere else first catches up by reseeding
+        let mut reseeded = headless_state(SoundLog::default());
+        for _ in 0..30 {
+            step(&mut reseeded, thrust);
+        }
+        for state in [&mut first, &mut second, &mut reseeded] {
+            state.reseed(866);
+            reset_game(state);
+        }
+        assert!(layout(&first) == layout(&second));
+        assert!(layout(&first) == layout(&reseeded));
+
+        for _ in 0..120 {
+            step(&mut first, thrust);
+            step(&mut second, thrust);
+        }
+        assert!(layout(&first) == layout(&second));
+    }
+
+    #[test]
+    fn a_new_game_after_a_high_score_starts_without_aliens() {
+        let mut state = headless_state(SoundLog::default());
+        state.score = 42_000;
+        state.last_score = state.score;
+        state.weapon = Weapon::Spread;
+        state.weapon_expires = state.now + WEAPON_DURATION;
+        state.rapid_fire_until = state.now + RAPID_FIRE_DURATION;
+        state.magnet_until = state.now + MAGNET_DURATION;
+        state.last_stand = 1.0;
+        state.shake = MAX_SHAKE;
+        state.game_state = GameState::GameOver;
+
+        reset_game(&mut state);
+        assert!(state.weapon == Weapon::Single);
+        assert!(state.weapon_expires <= state.now);
+        assert!(state.rapid_fire_until <= state.now);
+        assert!(state.magnet_until <= state.now);
+        assert_eq!(state.last_stand, 0.0);
+        assert_eq!(state.time_scale, 1.0);
+        assert_eq!(state.shake, 0.0);
+        step(&mut state, FrameInput::default());
+
+        assert!(state.aliens.is_empty());
+    }
+
+    #[test]
+    fn a_hitch_does_not_fast_forward_the_death_timer() {
+        let mut state = headless_state(SoundLog::default());
+        state.players[0].ship.status = ShipStatus::Dead(DeathTime::new(state.now, 3.0));
+        let died = state.now;
+
+        step_for(&mut state, 5.0, FrameInput::default());
+
+        assert!(state.now - died < 0.1);
+        assert!(matches!(state.ship().status, ShipStatus::Dead(_)));
+    }
+
+    #[test]
+    fn fire_skips_the_rest_of_the_respawn_delay() {
+        let mut state = headless_state(SoundLog::default());
+        state.players[0].ship.status = ShipStatus::Dead(DeathTime::new(state.now, 3.0));
+        let fire = FrameInput {
+            fire: true,
+            ..Default::default()
+        };
+
+        // too early, the explosion is still going on
+        step(&mut state, fire);
+        assert!(matches!(state.ship().status, ShipStatus::Dead(_)));
+
+        for _ in 0..72 {
+            step(&mut state, FrameInput::default());
+        }
+        step(&mut state, fire);
+        assert!(matches!(state.ship().status, ShipStatus::Alive));
+        assert_eq!(state.lifes(0), LIFES - 1);
+    }
+
+    #[test]
+    fn an_endless_run_takes_score_instead_of_lives() {
+        let mut state = headless_state(SoundLog::default());
+        state.run_mode = RunMode::Endless;
+        state.score = 12_000;
+        state.last_score = state.score;
+        state.players[0].ship.status = ShipStatus::Dead(DeathTime::new(state.now, 0.0));
+
+        step(&mut state, FrameInput::default());
+
+        assert!(matches!(state.ship().status, ShipStatus::Alive));
+        assert_eq!(state.lifes(0), LIFES);
+        assert!(state.score < 12_000);
+        assert!(state.ship().invulnerable_until > state.now);
+    }
+
+    #[test]
+    fn a_new_wave_keeps_its_rocks_away_from_the_ship() {
+        let mut state = headless_state(SoundLog::default());
+        for _ in 0..5 {
+            state.wave += 1;
+            reset_rocks(&mut state);
+            let ship = state.ship().position;
+            assert!(state
+                .rocks
+                .iter()
+                .all(|rock| wrapped_delta(ship, rock.position).length() > collision_cell_size()));
+        }
+    }
+
+    #[test]
+    fn the_rocks_of_a_new_wave_grow_in_before_they_can_hit() {
+        let mut state = headless_state(SoundLog::default());
+        state.players[0].ship.invulnerable_until = 0.0;
+        state.wave += 1;
+        reset_rocks(&mut state);
+        let stage_start = state.stage_start;
+        // one of them spawned right on top of the ship
+        state.rocks.truncate(1);
+        state.rocks[0].position = state.ship().position;
+        state.rocks[0].velocity = Vec2::ZERO;
+        assert_eq!(state.rocks[0].intro_scale(state.now), 0.0);
+
+        for _ in 0..15 {
+            step(&mut state, FrameInput::default());
+        }
+        assert!(matches!(state.ship().status, ShipStatus::Alive));
+        assert!(state.rocks[0].intro_scale(state.now) < 1.0);
+        // the wave itself started when the rocks appeared
+        assert_eq!(state.stage_start, stage_start);
+
+        for _ in 0..30 {
+            step(&mut state, FrameInput::default());
+        }
+        assert!(matches!(state.ship().status, ShipStatus::Dead(_)));
+    }
+
+    #[test]
+    fn a_seed_lays_out_the_same_waves() {
+        let waves = || {
+            let mut state = headless_state(SoundLog::default());
+            (0..4)
+                .map(|_| {
+                    state.wave += 1;
+                    reset_rocks(&mut state);
+                    state
+                        .rocks
+                        .iter()
+                        .map(|rock| rock.position)
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(waves(), waves());
+    }
+
+    #[test]
+    fn the_spawn_margin_keeps_new_rocks_off_the_edges() {
+        let mut state = headless_state(SoundLog::default());
+        let margin = SCALE * 3.0;
+        state.config.spawn_margin = margin;
+        // every pattern of spawning keeps to it, up to right on its line
+        let inside = |position: Vec2| {
+            let inner = SIZE - margin;
+            (margin..=inner.x).contains(&position.x) && (margin..=inner.y).contains(&position.y)
+        };
+        for _ in 0..5 {
+            state.wave += 1;
+            reset_rocks(&mut state);
+            assert!(state.rocks.iter().all(|rock| inside(rock.position)));
+        }
+    }
+
+    #[test]
+    fn the_last_stand_slows_the_world_but_not_the_clock() {
+        let mut state = headless_state(SoundLog::default());
+        *state.lifes_mut(0) = 0;
+        state.players[0].ship.invulnerable_until = 0.0;
+        // parked right beside the ship, but not touching it
+        let beside = state.ship().position + Vec2::new(SCALE * 2.0, 0.0);
+        state.rocks = vec![Rock {
+            position: beside,
+            size: RockSize::Small,
+            ..Default::default()
+        }];
+        let start = state.now;
+
+        for _ in 0..30 {
+            step(&mut state, FrameInput::default());
+        }
+
+        assert!(state.time_scale < 1.0);
+        assert!(state.last_stand > 0.9);
+        assert!((state.now - start - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn the_music_fades_between_menu_and_run_and_ducks_while_paused() {
+        let log = SoundLog::default();
+        let mut state = headless_state(log.clone());
+        let settle = |state: &mut State| {
+            for _ in 0..120 {
+                update_music(state, 1.0 / 60.0);
+            }
+        };
+
+        settle(&mut state);
+        assert_eq!((state.music.menu, state.music.game), (0.0, 1.0));
+        assert_eq!(
+            *log.played.borrow(),
+            vec![SoundEffect::MenuMusic, SoundEffect::GameMusic]
+        );
+
+        state.game_state = GameState::Paused;
+        settle(&mut state);
+        assert!((state.music.game - 0.3).abs() < 1e-4);
+
+        state.game_state = GameState::Menu;
+        update_music(&mut state, 0.15);
+        assert!(state.music.menu > 0.0 && state.music.game > 0.0);
+        settle(&mut state);
+        assert_eq!((state.music.menu, state.music.game), (1.0, 0.0));
+        // both keep looping, only their volumes change
+        assert_eq!(log.played.borrow().len(), 2);
+    }
+
+    #[test]
+    fn shots_only_carry_the_ships_momentum_when_asked_to() {
+        let fire = FrameInput {
+            fire: true,
+            ..Default::default()
+        };
+        let shot_velocity = |inherit: bool| {
+            let mut state = headless_state(SoundLog::default());
+            state.config.inherit_momentum = inherit;
+            // the first shot has to wait for the cooldown
+            while state.projectiles.is_empty() {
+                state.players[0].ship.velocity = Vec2::new(3.0, 0.0);
+                step(&mut state, fire);
+            }
+            state.projectiles[0].velocity
+        };
+
+        let classic = shot_velocity(false);
+        let newtonian = shot_velocity(true);
+        assert!((classic.length() - Config::default().shot_speed).abs() < 1e-3);
+        // a little less than what the ship was given, drag slows it before the shot
+        let carried = newtonian - classic;
+        assert!(carried.x > 2.5 && carried.x <= 3.0);
+        assert!(carried.y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn without_aliens_the_score_only_brings_rocks() {
+        let mut state = headless_state(SoundLog::default());
+        state.aliens_enabled = false;
+        state.score = 9000;
+        step(&mut state, FrameInput::default());
+        assert!(state.aliens.is_empty());
+
+        // the next wave still comes once the rocks are gone
+        let wave = state.wave;
+        state.rocks.clear();
+        step(&mut state, FrameInput::default());
+        assert_eq!(state.wave, wave + 1);
+    }
+
+    #[test]
+    fn the_score_brings_no_aliens_beyond_the_cap() {
+        let log = SoundLog::default();
+        let mut state = headless_state(log.clone());
+        state.config.max_aliens = 2;
+        // both thresholds are crossed at once
+        state.score = 40_000;
+        step(&mut state, FrameInput::default());
+        assert_eq!(state.aliens.len(), 2);
+
+        state.score = 80_000;
+        step(&mut state, FrameInput::default());
+        assert_eq!(state.aliens.len(), 2);
+        // a single drone for all of them
+        let drones = log
+            .played
+            .borrow()
+            .iter()
+            .filter(|sound| **sound == SoundEffect::AlienDrone)
+            .count();
+        assert_eq!(drones, 1);
+        assert!(state.alien_drone);
+    }
+
+    #[test]
+    fn clearing_a_wave_without_a_shot_unlocks_an_achievement_once() {
+        let mut state = headless_state(SoundLog::default());
+        state.rocks.clear();
+        step(&mut state, FrameInput::default());
+        assert!(state.achievements.is_unlocked(Achievement::Pacifist));
+        assert_eq!(state.toasts.len(), 1);
+
+        state.rocks.clear();
+        step(&mut state, FrameInput::default());
+        assert_eq!(state.stats.waves_without_firing, 2);
+        assert_eq!(state.toasts.len(), 1);
+    }
+
+    #[test]
+    fn a_bomb_leaves_out_the_fragments_beyond_the_rock_cap() {
+        let mut state = headless_state(SoundLog::default());
+        let ship = state.ship().position;
+        state.rocks = (0..4)
+            .map(|i| Rock {
+                position: ship + Vec2::new(SCALE * (2.0 + i as f32), 0.0),
+                size: RockSize::Big,
+                ..Default::default()
+            })
+            .collect();
+        state.config.max_rocks = 5;
+        state.players[0].bombs = 1;
+        step(
+            &mut state,
+            FrameInput {
+                bomb: true,
+                ..Default::default()
+            },
+        );
+
+        // all four broke and scored, only five of their eight fragments made it
+        assert_eq!(state.stats.total_rocks_destroyed(), 4);
+        assert_eq!(state.rocks.len(), 5);
+    }
+
+    #[test]
+    fn a_magnet_pulls_power_ups_but_leaves_the_rocks_alone() {
+        let mut state = headless_state(SoundLog::default());
+        let ship = state.ship().position;
+        let far = ship + Vec2::new(SCALE * 8.0, 0.0);
+        state.rocks = vec![Rock {
+            position: ship + Vec2::new(0.0, SCALE * 8.0),
+            ..Default::default()
+        }];
+        state.power_ups = vec![PowerUp::new(far, PowerUpKind::Shield)];
+
+        step(&mut state, FrameInput::default());
+        assert_eq!(state.power_ups[0].position, far);
+
+        state.magnet_until = state.now + MAGNET_DURATION;
+        for _ in 0..120 {
+            step(&mut state, FrameInput::default());
+        }
+        assert!(state.power_ups.is_empty());
+        assert!(state.players[0].ship.shielded);
+        assert_eq!(state.rocks[0].velocity, Vec2::ZERO);
+    }
+
+    #[test]
+    fn auto_fire_keeps_shooting_while_held_within_the_cap() {
+        let shots_while_held = |auto_fire: bool| {
+            let mut state = headless_state(SoundLog::default());
+            state.config.auto_fire = auto_fire;
+            for _ in 0..60 {
+                step(
+                    &mut state,
+                    FrameInput {
+                        fire_held: true,
+                        ..Default::default()
+                    },
+                );
+            }
+            (state.stats.shots_fired, state.players[0].ship.charge)
+        };
+
+        let (classic, charge) = shots_while_held(false);
+        assert_eq!(classic, 0);
+        assert!(charge > 0.0);
+        let (auto, charge) = shots_while_held(true);
+        // a second of holding, no faster than the cooldown allows
+        assert!(auto > 1 && auto <= 7);
+        assert_eq!(charge, 0.0);
+    }
+
+    #[test]
+    fn the_shop_opens_between_waves_and_its_ore_lasts_one_run() {
+        let mut state = headless_state(SoundLog::default());
+        state.config.shop = true;
+        state.rocks.clear();
+        step(&mut state, FrameInput::default());
+        assert!(state.game_state == GameState::Shop);
+        assert_eq!(state.wave, 2);
+        // the fire button still held from the wave can't buy the first line
+        assert_eq!(state.shop_lockout, SHOP_LOCKOUT);
+
+        state.credits = 20;
+        state.upgrades.fire_rate = 2;
+        reset_game(&mut state);
+        assert_eq!(state.credits, 0);
+        assert_eq!(state.upgrades.fire_rate, 0);
+    }
+
+    #[test]
+    fn a_wrapped_shot_only_hits_its_own_ship_when_asked_to() {
+        let survives_own_shot = |self_hit: bool| {
+            let mut state = headless_state(SoundLog::default());
+            state.config.self_hit = self_hit;
+            state.players[0].ship.invulnerable_until = 0.0;
+            // a single rock out of the way, so the wave doesn't end
+            state.rocks = vec![Rock {
+                position: Vec2::new(SCALE, SCALE),
+                ..Default::default()
+            }];
+            // heads out of the right edge and comes back in on the left, into the ship
+            let ship = state.ship().position;
+            let position = ship + Vec2::new(SIZE.x * 0.5 - SCALE, 0.0);
+            state.projectiles.push(Projectile {
+                position,
+                previous_position: position,
+                velocity: Vec2::new(10.0, 0.0),
+                state: ProjectileState::Alive { time_to_live: 2.0 },
+                owner: Owner::Player(0),
+                kind: ProjectileKind::Straight,
+                pierce: 0,
+                landed: false,
+                kills: 0,
+                kill_positions: Vec2::ZERO,
+            });
+            for _ in 0..90 {
+                step(&mut state, FrameInput::default());
+            }
+            matches!(state.ship().status, ShipStatus::Alive)
+        };
+
+        assert!(survives_own_shot(false));
+        assert!(!survives_own_shot(true));
+    }
+
+    #[test]
+    fn a_quick_restart_keeps_the_run_mode_and_its_score() {
+        let mut state = headless_state(SoundLog::default());
+        state.run_mode = RunMode::Endless;
+        state.endless_high_score = 0;
+        state.score = 4200;
+        state.wave = 3;
+        quick_restart(&mut state);
+
+        assert_eq!(state.endless_high_score, 4200);
+        // kept for the session, a headless state never writes it to the disk
+        assert_eq!(state.high_scores.endless, 4200);
+        assert!(state.run_mode == RunMode::Endless);
+        assert!(state.game_state == GameState::Playing);
+        assert_eq!((state.score, state.wave), (0, 1));
+    }
+
+    #[test]
+    fn a_quick_restart_of_a_zen_run_records_no_high_score() {
+        let mut state = headless_state(SoundLog::default());
+        state.zen = true;
+        state.high_score = 0;
+        state.score = 4200;
+        quick_restart(&mut state);
+
+        assert_eq!(state.high_score, 0);
+        assert!(!state.new_high_score);
+    }
+
+    #[test]
+    fn the_first_run_after_the_demo_draws_what_it_would_have_without() {
+        let mut state = headless_state(SoundLog::default());
+        let expected = state.random.gen::<u64>();
+
+        let mut state = headless_state(SoundLog::default());
+        state.attract = true;
+        for _ in 0..120 {
+            step(&mut state, FrameInput::default());
+        }
+        stop_attract(&mut state);
+        assert_eq!(state.random.gen::<u64>(), expected);
+    }
+
+    #[test]
+    fn precision_flight_brakes_but_still_drifts() {
+        let glide = |flight_mode: FlightMode| {
+            let mut state = headless_state(SoundLog::default());
+            state.flight_mode = flight_mode;
+            let start = state.ship().position;
+            state.players[0].ship.velocity = Vec2::new(5.0, 0.0);
+            for _ in 0..60 {
+                step(&mut state, FrameInput::default());
+            }
+            let ship = state.ship();
+            (ship.velocity.length(), (ship.position - start).length())
+        };
+
+        let (classic_speed, classic_distance) = glide(FlightMode::Classic);
+        let (precision_speed, precision_distance) = glide(FlightMode::Precision);
+        assert!(classic_speed > 1.0);
+        assert!(precision_speed < classic_speed * 0.1);
+        assert!(precision_distance < classic_distance);
+        // not stopped dead the moment the thrust lets go
+        assert!(precision_distance > SCALE);
+    }
+
+    #[test]
+    fn thrust_stops_adding_speed_at_the_limit_without_drag() {
+        let mut state = headless_state(SoundLog::default());
+        state.config.drag = 0.0;
+        state.players[0].ship.invulnerable_until = f32::MAX;
+        let thrust = FrameInput {
+            thrust: true,
+            ..Default::default()
+        };
+        // long enough to reach twice the limit if nothing held it back
+        for _ in 0..100 {
+            step(&mut state, thrust);
+        }
+
+        assert!(state.ship().velocity.length() <= 20.0 + 1e-3);
+    }
+
+    #[test]
+    fn twin_stick_thrusts_along_the_screen_whatever_the_ship_faces() {
+        let mut state = headless_state(SoundLog::default());
+        state.control_scheme = ControlScheme::TwinStick;
+        // up and to the right, while the ship turns to face the cursor on the left
+        let input = FrameInput {
+            thrust: true,
+            right: true,
+            pointer: state.ship().position - Vec2::new(SCALE * 5.0, 0.0),
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            step(&mut state, input);
+        }
+
+        let expected = Vec2::new(1.0, -1.0).normalize();
+        assert!(state.ship().velocity.normalize().dot(expected) > 0.999);
+        assert!(state.players[0].thrust_direction.dot(expected) > 0.999);
+        let facing = Vec2::from_angle(state.ship().rotation + std::f32::consts::PI * 0.5);
+        assert!(facing.dot(Vec2::NEG_X) > 0.9);
+    }
+
+    #[test]
+    fn the_gravity_well_swallows_a_rock_whole_and_pays_once() {
+        let mut state = headless_state(SoundLog::default());
+        let well = GravityWell::new(Vec2::new(SCALE * 4.0, SCALE * 4.0));
+        state.gravity_well = Some(well);
+        // a second rock far off keeps the wave from ending
+        state.rocks = vec![
+            Rock {
+                position: well.position,
+                size: RockSize::Big,
+                ..Default::default()
+            },
+            Rock {
+                position: SIZE - SCALE * 4.0,
+                size: RockSize::Small,
+                ..Default::default()
+            },
+        ];
+        let combo = state.combo.multiplier;
+        for _ in 0..30 {
+            step(&mut state, FrameInput::default());
+        }
+
+        assert_eq!(state.rocks.len(), 1);
+        assert!(state.rocks[0].size == RockSize::Small);
+        assert_eq!(state.score, RockSize::Big.get_score());
+        assert_eq!(state.combo.multiplier, combo);
+    }
+
+    #[test]
+    fn only_a_real_run_moves_the_skill_estimate() {
+        let skill_after_a_quick_wave = |attract: bool, zen: bool| {
+            let mut state = headless_state(SoundLog::default());
+            state.attract = attract;
+            state.zen = zen;
+            state.rocks.clear();
+            step(&mut state, FrameInput::default());
+            assert_eq!(state.wave, 2);
+            state.skill.level
+        };
+
+        assert!(skill_after_a_quick_wave(false, false) > 1.0);
+        assert_eq!(skill_after_a_quick_wave(true, false), 1.0);
+        assert_eq!(skill_after_a_quick_wave(false, true), 1.0);
+    }
+
+    #[test]
+    fn a_daily_challenge_plays_with_the_defaults_and_gives_the_player_theirs_back() {
+        let mut state = headless_state(SoundLog::default());
+        state.config.shot_speed = 20.0;
+        state.config.adaptive_difficulty = true;
+        state.config.vignette = true;
+        state.density = Density::Dense;
+        state.aliens_enabled = false;
+        state.start_lifes = 5;
+
+        start_daily(&mut state);
+        // started over, which must not put the defaults aside as the player's own
+        start_daily(&mut state);
+        assert_eq!(state.config.shot_speed, Config::default().shot_speed);
+        assert!(!state.config.adaptive_difficulty);
+        assert!(state.config.vignette);
+        assert!(state.density == Density::Normal);
+        assert!(state.aliens_enabled);
+        assert_eq!(state.lifes, LIFES);
+        // a volume change saved meanwhile keeps the player's own density
+        assert!(state.settings().density == Density::Dense);
+
+        end_daily(&mut state);
+        assert!(state.daily.is_none());
+        assert_eq!(state.config.shot_speed, 20.0);
+        assert!(state.config.adaptive_difficulty);
+        assert!(state.density == Density::Dense);
+        assert!(!state.aliens_enabled);
+        assert_eq!(state.start_lifes, 5);
+    }
+
+    #[test]
+    fn rapid_fire_shoots_more_often() {
+        let held = FrameInput {
+            fire_held: true,
+            ..Default::default()
+        };
+        let shots_in_half_a_second = |rapid: bool| {
+            let mut state = headless_state(SoundLog::default());
+            if rapid {
+                state.rapid_fire_until = state.now + RAPID_FIRE_DURATION;
+            }
+            step(&mut state, FrameInput { fire: true, ..held });
+            for _ in 0..29 {
+                step(&mut state, held);
+            }
+            state.stats.shots_fired
+        };
+
+        assert!(shots_in_half_a_second(true) > 2 * shots_in_half_a_second(false));
+    }
+
+    #[test]
+    fn a_reset_mid_thrust_silences_the_thruster() {
+        let audio = SoundLog::default();
+        let mut state = headless_state(audio.clone());
+        let thrust = FrameInput {
+            thrust: true,
+            ..Default::default()
+        };
+        step(&mut state, thrust);
+        assert!(audio.played.borrow().contains(&SoundEffect::Thruster));
+
+        reset_game(&mut state);
+
+        assert!(audio.stopped.borrow().contains(&SoundEffect::Thruster));
+    }
+
+    #[test]
+    fn nothing_destroys_the_ship_in_zen_mode() {
+        let audio = SoundLog::default();
+        let mut state = headless_state(audio.clone());
+        state.zen = true;
+        state.rocks = vec![Rock {
+            position: state.ship().position,
+            size: RockSize::Medium,
+            ..Default::default()
+        }];
+
+        for _ in 0..120 {
+            step(&mut state, FrameInput::default());
+        }
+
+        assert!(matches!(state.ship().status, ShipStatus::Alive));
+        assert!(!audio.played.borrow().contains(&SoundEffect::BlopLow));
+    }
+
+    #[test]
+    fn a_fresh_fragment_on_the_ship_does_not_kill_it_right_away() {
+        let mut state = headless_state(SoundLog::default());
+        let mut fragments = vec![Rock {
+            position: state.ship().position,
+            size: RockSize::Medium,
+            ..Default::default()
+        }];
+        grant_fragment_grace(&mut fragments, state.now);
+        state.rocks = fragments;
+
+        step(&mut state, FrameInput::default());
+        assert!(matches!(state.ship().status, ShipStatus::Alive));
+
+        for _ in 0..20 {
+            step(&mut state, FrameInput::default());
+        }
+        assert!(matches!(state.ship().status, ShipStatus::Dead(_)));
+    }
+
+    #[test]
+    fn only_several_kills_by_one_shot_earn_a_chain_bonus() {
+        assert_eq!(chain_bonus(1), 0);
+        assert!(chain_bonus(2) > 0);
+        assert!(chain_bonus(3) > chain_bonus(2) * 3 / 2);
+    }
+
+    #[test]
+    fn shooting_a_rock_destroys_it_and_scores() {
+        let audio = SoundLog::default();
+        let mut state = headless_state(audio.clone());
+        // the ship starts out facing down, one rock is right in front of it
+        let ship = state.ship().position;
+        state.rocks = vec![
+            Rock {
+                position: ship + Vec2::new(0.0, SCALE * 5.0),
+                size: RockSize::Small,
+                ..Default::default()
+            },
+            // and one out of the way, so the wave isn't cleared
+            Rock {
+                position: ship + Vec2::new(SCALE * 8.0, -SCALE * 8.0),
+                size: RockSize::Small,
+                ..Default::default()
+            },
+        ];
+        state.aliens.clear();
+        // past the cooldown of a shot at time zero
+        for _ in 0..30 {
+            step(&mut state, FrameInput::default());
+        }
+
+        step(
+            &mut state,
+            FrameInput {
+                fire: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(state.projectiles.len(), 1);
+        for _ in 0..60 {
+            step(&mut state, FrameInput::default());
+        }
+
+        assert_eq!(state.rocks.len(), 1);
+        assert_eq!(state.score, RockSize::Small.get_score());
+        assert!(state.ship().is_vulnerable(state.now));
+        let played = audio.played.borrow();
+        assert!(played.contains(&SoundEffect::Shoot));
+        assert!(played.contains(&SoundEffect::Asteroid));
+    }
+}
@@ -0,0 +1,96 @@
+use macroquad::prelude::*;
+
+use crate::{
+    config::Config,
+    game::{advance_clock, reset_game, update, GameState, State},
+    input::{InputProvider, ReplayInput},
+    settings::Settings,
+    sound::silence,
+};
+
+/// Where the first ship of a recorded run was at some point of it
+#[derive(Clone, Copy)]
+pub(crate) struct GhostSample {
+    // seconds since the start of the run
+    pub(crate) time: f32,
+    pub(crate) position: Vec2,
+    pub(crate) rotation: f32,
+    pub(crate) alive: bool,
+}
+
+/// A past run to race against, only ever drawn and never part of the simulation
+pub(crate) struct GhostTrack {
+    samples: Vec<GhostSample>,
+}
+
+impl GhostTrack {
+    /// Plays `replay` through headless up to its end or game over, sampling the ship
+    /// after every step. The world of the recording is thrown away, only the ship is kept.
+    pub(crate) fn record(mut replay: ReplayInput, mut config: Config) -> Self {
+        // the run was recorded without the shop, see main
+        config.shop = false;
+        // played with the settings it was recorded with, not the ones of the player
+        let mut settings = Settings::default();
+        replay.settings.apply(&mut settings);
+        let mut state = State::new(silence(), settings, config, Some(replay.seed));
+        // like the demo it doesn't get to keep a high score
+        state.attract = true;
+        reset_game(&mut state);
+        state.game_state = GameState::Playing;
+
+        let mut samples = vec![];
+        loop {
+            if state.game_state != GameState::Playing {
+                break;
+            }
+            let Some(step) = replay.next_step(&state) else {
+                break;
+            };
+            advance_clock(&mut state, step.delta);
+            update(&mut state, &step.inputs);
+            state.frame += 1;
+            let ship = state.ship();
+            samples.push(GhostSample {
+                time: state.now - state.run_start,
+                position: ship.position,
+                rotation: ship.rotation,
+                alive: (&ship.status).into(),
+            });
+        }
+        Self { samples }
+    }
+
+    /// The ship `elapsed` seconds into the run, frozen at the end once the recording is over
+    pub(crate) fn at(&self, elapsed: f32) -> Option<GhostSample> {
+        let after = self
+            .samples
+            .partition_point(|sample| sample.time <= elapsed);
+        self.samples.get(after.saturating_sub(1)).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: f32, x: f32) -> GhostSample {
+        GhostSample {
+            time,
+            position: Vec2::new(x, 0.0),
+            rotation: 0.0,
+            alive: true,
+        }
+    }
+
+    #[test]
+    fn freezes_at_the_last_sample() {
+        let track = GhostTrack {
+            samples: vec![sample(0.1, 1.0), sample(0.2, 2.0), sample(0.3, 3.0)],
+        };
+
+        assert_eq!(track.at(0.0).unwrap().position.x, 1.0);
+        assert_eq!(track.at(0.25).unwrap().position.x, 2.0);
+        assert_eq!(track.at(10.0).unwrap().position.x, 3.0);
+        assert!(GhostTrack { samples: vec![] }.at(1.0).is_none());
+    }
+}
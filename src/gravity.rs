@@ -0,0 +1,56 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::WrapMode, game::playfield_delta, SCALE};
+
+/// A stationary hazard of later waves pulling rocks, ships and projectiles towards it.
+/// Whatever reaches its core is destroyed.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct GravityWell {
+    pub(crate) position: Vec2,
+}
+
+impl GravityWell {
+    // the first wave a well may show up in
+    pub(crate) const FIRST_WAVE: usize = 5;
+    pub(crate) const CORE_RADIUS: f32 = SCALE * 0.6;
+    // acceleration at a distance of one pixel, falling off with the distance
+    const STRENGTH: f32 = 800.0;
+    // closer than this the pull stops growing, so nothing is flung away at absurd speeds
+    const MIN_DISTANCE: f32 = SCALE * 1.5;
+
+    pub(crate) fn new(position: Vec2) -> Self {
+        Self { position }
+    }
+
+    /// Acceleration per second on something at `position`
+    pub(crate) fn pull(&self, position: Vec2, wrap_mode: WrapMode) -> Vec2 {
+        let delta = playfield_delta(wrap_mode, position, self.position);
+        let distance = delta.length().max(Self::MIN_DISTANCE);
+        delta.normalize_or_zero() * Self::STRENGTH / distance
+    }
+
+    /// Whether a circle around `position` touches the core
+    pub(crate) fn swallows(&self, position: Vec2, radius: f32, wrap_mode: WrapMode) -> bool {
+        playfield_delta(wrap_mode, position, self.position).length() < Self::CORE_RADIUS + radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SIZE;
+
+    #[test]
+    fn pull_falls_off_and_is_capped_near_the_core() {
+        let well = GravityWell::new(SIZE * 0.5);
+        let far = well.pull(well.position + Vec2::new(SCALE * 10.0, 0.0), WrapMode::Wrap);
+        let near = well.pull(well.position + Vec2::new(SCALE * 2.0, 0.0), WrapMode::Wrap);
+        assert!(far.x < 0.0 && near.x < far.x);
+
+        let at_min = well.pull(well.position + Vec2::new(SCALE * 1.5, 0.0), WrapMode::Wrap);
+        let inside = well.pull(well.position + Vec2::new(1.0, 0.0), WrapMode::Wrap);
+        assert!((at_min.length() - inside.length()).abs() < 1e-3);
+        assert_eq!(well.pull(well.position, WrapMode::Wrap), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use crate::SIZE;
+
+/// Buckets indices by position into a grid that wraps like the playfield, so a query
+/// only looks at neighbouring cells instead of everything on the field
+pub(crate) struct SpatialGrid {
+    cell_size: f32,
+    columns: i32,
+    rows: i32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    /// Anything closer than `cell_size` ends up in the same or a neighbouring cell,
+    /// over the edges of the playfield as well
+    pub(crate) fn new(cell_size: f32) -> Self {
+        // rounded down so no cell is smaller than `cell_size`, the last ones take the rest
+        Self {
+            cell_size,
+            columns: ((SIZE.x / cell_size) as i32).max(1),
+            rows: ((SIZE.y / cell_size) as i32).max(1),
+            cells: HashMap::new(),
+        }
+    }
+
+    /// A grid holding the index of every position
+    pub(crate) fn from_positions(cell_size: f32, positions: impl Iterator<Item = Vec2>) -> Self {
+        let mut grid = Self::new(cell_size);
+        for (index, position) in positions.enumerate() {
+            grid.insert(index, position);
+        }
+        grid
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, position: Vec2) {
+        self.cells
+            .entry(self.cell_of(position))
+            .or_default()
+            .push(index);
+    }
+
+    /// Indices in the cell of `position` and the eight around it, in ascending order
+    /// so callers visit them in the same order as a plain loop would
+    pub(crate) fn near(&self, position: Vec2) -> Vec<usize> {
+        let (column, row) = self.cell_of(position);
+        let mut indices = vec![];
+        for offset_x in -1..=1 {
+            for offset_y in -1..=1 {
+                let cell = (
+                    (column + offset_x).rem_euclid(self.columns),
+                    (row + offset_y).rem_euclid(self.rows),
+                );
+                if let Some(cell) = self.cells.get(&cell) {
+                    indices.extend_from_slice(cell);
+                }
+            }
+        }
+        // a grid less than three cells wide visits some cells twice
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// The area of every occupied cell with the number of indices in it
+    pub(crate) fn occupancy(&self) -> impl Iterator<Item = (Rect, usize)> + '_ {
+        self.cells
+            .iter()
+            .map(|(&cell, indices)| (self.cell_rect(cell), indices.len()))
+    }
+
+    fn cell_rect(&self, (column, row): (i32, i32)) -> Rect {
+        let x = column as f32 * self.cell_size;
+        let y = row as f32 * self.cell_size;
+        // the last column and row stretch to the edge
+        let width = if column == self.columns - 1 {
+            SIZE.x - x
+        } else {
+            self.cell_size
+        };
+        let height = if row == self.rows - 1 {
+            SIZE.y - y
+        } else {
+            self.cell_size
+        };
+        Rect::new(x, y, width, height)
+    }
+
+    fn cell_of(&self, position: Vec2) -> (i32, i32) {
+        (
+            ((position.x / self.cell_size) as i32).clamp(0, self.columns - 1),
+            ((position.y / self.cell_size) as i32).clamp(0, self.rows - 1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::rand::Rng;
+    use rand_xoshiro::{rand_core::SeedableRng, Xoshiro256PlusPlus};
+
+    use super::*;
+    use crate::{game::wrapped_delta, rock::collision_cell_size};
+
+    fn random_positions(random: &mut Xoshiro256PlusPlus, count: usize) -> Vec<Vec2> {
+        (0..count)
+            .map(|_| Vec2::new(random.gen::<f32>() * SIZE.x, random.gen::<f32>() * SIZE.y))
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_same_collisions_as_brute_force() {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(809);
+        let cell_size = collision_cell_size();
+        let radius = cell_size * 0.5;
+        let rocks = random_positions(&mut random, 200);
+        let projectiles = random_positions(&mut random, 300);
+
+        let grid = SpatialGrid::from_positions(cell_size, projectiles.iter().copied());
+        for rock in rocks.iter() {
+            let brute_force: Vec<usize> = (0..projectiles.len())
+                .filter(|&index| rock.distance(projectiles[index]) < radius)
+                .collect();
+            let found: Vec<usize> = grid
+                .near(*rock)
+                .into_iter()
+                .filter(|&index| rock.distance(projectiles[index]) < radius)
+                .collect();
+            assert_eq!(found, brute_force);
+
+            // the same holds for distances measured over the edges
+            let brute_force: Vec<usize> = (0..projectiles.len())
+                .filter(|&index| wrapped_delta(*rock, projectiles[index]).length() < cell_size)
+                .collect();
+            let found: Vec<usize> = grid
+                .near(*rock)
+                .into_iter()
+                .filter(|&index| wrapped_delta(*rock, projectiles[index]).length() < cell_size)
+                .collect();
+            assert_eq!(found, brute_force);
+        }
+    }
+
+    #[test]
+    fn occupied_cells_hold_their_positions() {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(853);
+        let positions = random_positions(&mut random, 50);
+        let grid = SpatialGrid::from_positions(collision_cell_size(), positions.iter().copied());
+
+        let mut total = 0;
+        for (rect, count) in grid.occupancy() {
+            let inside = positions
+                .iter()
+                .filter(|position| rect.contains(**position))
+                .count();
+            assert_eq!(inside, count);
+            total += count;
+        }
+        assert_eq!(total, positions.len());
+    }
+}
@@ -0,0 +1,45 @@
+use std::{collections::BTreeMap, fs};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const HIGH_SCORE_PATH: &str = "./highscore.toml";
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct HighScore {
+    pub(crate) score: usize,
+    // endless runs don't compare to arcade ones, so they keep their own
+    pub(crate) endless: usize,
+    // the best daily challenge of each day, by date
+    pub(crate) daily: BTreeMap<String, usize>,
+    // only the one the game loaded at startup writes back to the disk, so headless runs
+    // and tests leave the player's scores alone
+    #[serde(skip)]
+    persistent: bool,
+}
+
+impl HighScore {
+    pub(crate) fn load() -> Self {
+        let mut high_score: Self = fs::read_to_string(HIGH_SCORE_PATH)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default();
+        high_score.persistent = true;
+        high_score
+    }
+
+    pub(crate) fn save(&self) {
+        if !self.persistent {
+            return;
+        }
+        match toml::to_string(self) {
+            Ok(content) => {
+                if let Err(err) = fs::write(HIGH_SCORE_PATH, content) {
+                    warn!("Unable to write high score: {}", err);
+                }
+            }
+            Err(err) => warn!("Unable to serialize high score: {}", err),
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    fs::{self, File},
+    io::{self, LineWriter, Write},
+};
+
+use gilrs::{Axis, Button, Event, EventType, Gilrs};
+use macroquad::prelude::*;
+
+use crate::{
+    config::KeyBindings,
+    game::{State, FRAME_STEP},
+    render::{playfield_origin, playfield_scale},
+    settings::RunSettings,
+};
+
+/// Input read from the first connected gamepad during a frame
+#[derive(Default)]
+pub(crate) struct GamepadInput {
+    // -1.0 turns fully right, 1.0 fully left
+    pub(crate) rotation: f32,
+    pub(crate) thrust: bool,
+    pub(crate) fire: bool,
+    pub(crate) fire_held: bool,
+    pub(crate) dash: bool,
+    pub(crate) bomb: bool,
+}
+
+pub(crate) struct Gamepads {
+    pub(crate) gilrs: Option<Gilrs>,
+}
+
+impl Gamepads {
+    const DEAD_ZONE: f32 = 0.15;
+
+    pub(crate) fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                warn!("Gamepad support unavailable: {}", err);
+                None
+            }
+        };
+        Self { gilrs }
+    }
+
+    pub(crate) fn poll(&mut self) -> GamepadInput {
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return GamepadInput::default();
+        };
+
+        let mut input = GamepadInput::default();
+        while let Some(Event { event, .. }) = gilrs.next_event() {
+            match event {
+                EventType::ButtonPressed(Button::West | Button::RightTrigger, _) => {
+                    input.fire = true;
+                }
+                EventType::ButtonPressed(Button::East, _) => input.dash = true,
+                EventType::ButtonPressed(Button::North, _) => input.bomb = true,
+                _ => {}
+            }
+        }
+
+        // Looked up every frame instead of cached so controllers can be (un)plugged mid game
+        let Some((_, gamepad)) = gilrs.gamepads().find(|(_, gamepad)| gamepad.is_connected())
+        else {
+            return input;
+        };
+
+        let stick = gamepad.value(Axis::LeftStickX);
+        if gamepad.is_pressed(Button::DPadLeft) {
+            input.rotation = 1.0;
+        } else if gamepad.is_pressed(Button::DPadRight) {
+            input.rotation = -1.0;
+        } else if stick.abs() > Self::DEAD_ZONE {
+            input.rotation = -stick;
+        }
+        input.thrust =
+            gamepad.is_pressed(Button::South) || gamepad.is_pressed(Button::RightTrigger2);
+        input.fire_held =
+            gamepad.is_pressed(Button::West) || gamepad.is_pressed(Button::RightTrigger);
+        input
+    }
+}
+
+/// Everything `update` reads from the player during one step
+#[derive(Clone, Copy, Default)]
+pub(crate) struct FrameInput {
+    pub(crate) left: bool,
+    pub(crate) right: bool,
+    // analog steering from a gamepad, same range as `GamepadInput::rotation`
+    pub(crate) turn: f32,
+    pub(crate) thrust: bool,
+    // only the twin-stick controls thrust backwards, everywhere else it does nothing
+    pub(crate) reverse: bool,
+    pub(crate) fire: bool,
+    pub(crate) fire_held: bool,
+    // mouse cursor in playfield coordinates
+    pub(crate) pointer: Vec2,
+    pub(crate) toggle_control_scheme: bool,
+    pub(crate) dash: bool,
+    pub(crate) bomb: bool,
+}
+
+impl FrameInput {
+    // fields per player in a replay line
+    const FIELDS: usize = 12;
+    // what replays recorded before the bomb had, the fields added since are optional
+    const MIN_FIELDS: usize = 10;
+
+    fn to_fields(self) -> String {
+        let flag = |value: bool| if value { 1 } else { 0 };
+        format!(
+            "{} {} {} {} {} {} {} {} {} {} {} {}",
+            flag(self.left),
+            flag(self.right),
+            self.turn,
+            flag(self.thrust),
+            flag(self.fire),
+            flag(self.fire_held),
+            self.pointer.x,
+            self.pointer.y,
+            flag(self.toggle_control_scheme),
+            flag(self.dash),
+            flag(self.bomb),
+            flag(self.reverse),
+        )
+    }
+
+    /// Reads what `to_fields` wrote, missing trailing fields of an older replay aren't pressed
+    fn from_fields(fields: &[&str]) -> Option<Self> {
+        if fields.len() < Self::MIN_FIELDS || fields.len() > Self::FIELDS {
+            return None;
+        }
+        let field = |index: usize| fields.get(index).copied().unwrap_or("0");
+        let [left, right, turn, thrust, fire, fire_held, x, y, toggle, dash, bomb, reverse] =
+            std::array::from_fn(field);
+        let flag = |value: &str| value == "1";
+        Some(Self {
+            left: flag(left),
+            right: flag(right),
+            turn: turn.parse().ok()?,
+            thrust: flag(thrust),
+            reverse: flag(reverse),
+            fire: flag(fire),
+            fire_held: flag(fire_held),
+            pointer: Vec2::new(x.parse().ok()?, y.parse().ok()?),
+            toggle_control_scheme: flag(toggle),
+            dash: flag(dash),
+            bomb: flag(bomb),
+        })
+    }
+}
+
+/// One simulation step, the frame time is part of it so a replay advances exactly like the recording
+#[derive(Clone)]
+pub(crate) struct Step {
+    pub(crate) delta: f32,
+    // one per player
+    pub(crate) inputs: Vec<FrameInput>,
+}
+
+impl Step {
+    fn to_line(&self) -> String {
+        let mut line = self.delta.to_string();
+        for input in self.inputs.iter() {
+            line.push(' ');
+            line.push_str(&input.to_fields());
+        }
+        line
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (delta, players) = fields.split_first()?;
+        // the most fields per player that add up, older replays have fewer. With at most
+        // two players no count can be mistaken for another.
+        let per_player = (FrameInput::MIN_FIELDS..=FrameInput::FIELDS)
+            .rev()
+            .find(|count| !players.is_empty() && players.len() % count == 0)?;
+        Some(Self {
+            delta: delta.parse().ok()?,
+            inputs: players
+                .chunks(per_player)
+                .map(FrameInput::from_fields)
+                .collect::<Option<Vec<FrameInput>>>()?,
+        })
+    }
+}
+
+pub(crate) trait InputProvider {
+    /// Input for the next step, `None` once there is nothing left to play.
+    /// The `state` is only looked at by providers playing by themselves.
+    fn next_step(&mut self, state: &State) -> Option<Step>;
+}
+
+/// Reads keyboard, mouse and gamepads, optionally writing every step to a replay file
+pub(crate) struct LiveInput {
+    gamepads: Gamepads,
+    recorder: Option<LineWriter<File>>,
+    // the settings are written along with the first step, they may change in the menu
+    settings_recorded: bool,
+    players: usize,
+    bindings: KeyBindings,
+}
+
+impl LiveInput {
+    pub(crate) fn new(players: usize, bindings: KeyBindings) -> Self {
+        Self {
+            gamepads: Gamepads::new(),
+            recorder: None,
+            settings_recorded: false,
+            players,
+            bindings,
+        }
+    }
+
+    /// Starts recording into `path`, the `seed` and the `RunSettings` are stored so
+    /// playback can recreate the run
+    pub(crate) fn record(&mut self, path: &str, seed: u64) -> io::Result<()> {
+        let mut file = LineWriter::new(File::create(path)?);
+        writeln!(file, "seed {}", seed)?;
+        self.recorder = Some(file);
+        Ok(())
+    }
+}
+
+impl InputProvider for LiveInput {
+    fn next_step(&mut self, state: &State) -> Option<Step> {
+        let gamepad = self.gamepads.poll();
+        let keys = get_keys_down();
+        let bindings = &self.bindings;
+        let mut inputs = vec![FrameInput {
+            left: keys.contains(&bindings.left.0),
+            right: keys.contains(&bindings.right.0),
+            turn: gamepad.rotation,
+            thrust: keys.contains(&bindings.thrust.0) || gamepad.thrust,
+            reverse: keys.contains(&bindings.reverse.0),
+            fire: is_key_pressed(bindings.fire.0)
+                || is_mouse_button_pressed(MouseButton::Left)
+                || gamepad.fire,
+            fire_held: keys.contains(&bindings.fire.0)
+                || is_mouse_button_down(MouseButton::Left)
+                || gamepad.fire_held,
+            pointer: mouse_playfield_position(state.config.render_scale),
+            toggle_control_scheme: is_key_pressed(KeyCode::C),
+            dash: is_key_pressed(bindings.dash.0) || gamepad.dash,
+            bomb: is_key_pressed(bindings.bomb.0) || gamepad.bomb,
+        }];
+        // the second co-op ship sits on the right side of the keyboard
+        if self.players > 1 {
+            inputs.push(FrameInput {
+                left: keys.contains(&KeyCode::Left),
+                right: keys.contains(&KeyCode::Right),
+                thrust: keys.contains(&KeyCode::Up),
+                reverse: keys.contains(&KeyCode::Down),
+                fire: is_key_pressed(KeyCode::RightShift),
+                fire_held: keys.contains(&KeyCode::RightShift),
+                dash: is_key_pressed(KeyCode::RightControl),
+                bomb: is_key_pressed(KeyCode::Enter),
+                ..Default::default()
+            });
+        }
+        // a frame step simulates a fixed time however long the frame took, that is
+        // what gets recorded so the replay plays the same
+        let delta = if state.frame_stepping() {
+            FRAME_STEP
+        } else {
+            get_frame_time()
+        };
+        let step = Step { delta, inputs };
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            let mut written = Ok(());
+            if !self.settings_recorded {
+                let header = RunSettings::of(&state.settings()).to_header();
+                written = writeln!(recorder, "{}", header);
+                self.settings_recorded = true;
+            }
+            if let Err(err) = written.and_then(|()| writeln!(recorder, "{}", step.to_line())) {
+                warn!("Stopped recording: {}", err);
+                self.recorder = None;
+            }
+        }
+        Some(step)
+    }
+}
+
+/// Plays back the steps of a file written by `LiveInput::record`
+pub(crate) struct ReplayInput {
+    pub(crate) seed: u64,
+    pub(crate) settings: RunSettings,
+    steps: std::vec::IntoIter<Step>,
+}
+
+impl ReplayInput {
+    pub(crate) fn load(path: &str) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines();
+        let seed = lines
+            .next()
+            .and_then(|line| line.strip_prefix("seed "))
+            .and_then(|seed| seed.parse().ok())
+            .ok_or_else(|| invalid("missing seed header"))?;
+        // older replays go straight to the steps, they were played with the defaults
+        let mut lines = lines.peekable();
+        let settings = lines.peek().and_then(|line| RunSettings::from_header(line));
+        if settings.is_some() {
+            lines.next();
+        }
+        let steps = lines
+            .map(Step::from_line)
+            .collect::<Option<Vec<Step>>>()
+            .ok_or_else(|| invalid("malformed step"))?;
+
+        Ok(Self {
+            seed,
+            settings: settings.unwrap_or_default(),
+            steps: steps.into_iter(),
+        })
+    }
+}
+
+impl InputProvider for ReplayInput {
+    fn next_step(&mut self, _state: &State) -> Option<Step> {
+        self.steps.next()
+    }
+}
+
+/// Hands out prepared inputs for the first player at a steady 60 frames per second,
+/// stands in for the live input in tests
+#[cfg(test)]
+pub(crate) struct ScriptedInput {
+    inputs: std::vec::IntoIter<FrameInput>,
+}
+
+#[cfg(test)]
+impl ScriptedInput {
+    pub(crate) fn new(inputs: Vec<FrameInput>) -> Self {
+        Self {
+            inputs: inputs.into_iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl InputProvider for ScriptedInput {
+    fn next_step(&mut self, _state: &State) -> Option<Step> {
+        Some(Step {
+            delta: 1.0 / 60.0,
+            inputs: vec![self.inputs.next()?],
+        })
+    }
+}
+
+/// Mouse cursor translated from window pixels into logical playfield coordinates,
+/// undoing the letterboxing and `render_scale` of the playfield camera
+pub(crate) fn mouse_playfield_position(render_scale: f32) -> Vec2 {
+    (Vec2::from(mouse_position()) - playfield_origin(render_scale)) / playfield_scale(render_scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_replay_from_before_the_bomb_reads_it_as_not_pressed() {
+        let step = Step::from_line("0.016 0 1 0 1 1 1 100 200 0 0").unwrap();
+        assert_eq!(step.inputs.len(), 1);
+        let input = step.inputs[0];
+        assert!(input.right && input.thrust && input.fire);
+        assert_eq!(input.pointer, Vec2::new(100.0, 200.0));
+        assert!(!input.bomb);
+
+        let input = FrameInput {
+            bomb: true,
+            ..Default::default()
+        };
+        let line = Step {
+            delta: 0.016,
+            inputs: vec![input, input],
+        }
+        .to_line();
+        let step = Step::from_line(&line).unwrap();
+        assert_eq!(step.inputs.len(), 2);
+        assert!(step.inputs[1].bomb);
+    }
+
+    #[test]
+    fn a_replay_from_before_reverse_thrust_reads_it_as_not_pressed() {
+        let step = Step::from_line("0.016 0 0 0 1 0 0 0 0 0 0 1").unwrap();
+        assert!(step.inputs[0].thrust && step.inputs[0].bomb);
+        assert!(!step.inputs[0].reverse);
+
+        let step = Step::from_line("0.016 0 0 0 0 0 0 0 0 0 0 0 1").unwrap();
+        assert!(step.inputs[0].reverse);
+    }
+}
@@ -0,0 +1,181 @@
+use macroquad::prelude::*;
+
+use crate::game::{State, MAX_LIFES, VOLUME_STEP};
+
+/// One line of the options screen
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum OptionItem {
+    Volume,
+    MusicVolume,
+    Muted,
+    Theme,
+    Density,
+    AlienIndicators,
+    Aliens,
+    FlightMode,
+    StartingLives,
+}
+
+impl OptionItem {
+    pub(crate) const ALL: [OptionItem; 9] = [
+        OptionItem::Volume,
+        OptionItem::MusicVolume,
+        OptionItem::Muted,
+        OptionItem::Theme,
+        OptionItem::Density,
+        OptionItem::AlienIndicators,
+        OptionItem::Aliens,
+        OptionItem::FlightMode,
+        OptionItem::StartingLives,
+    ];
+
+    /// The lines shown, the music volume only once there is music to play
+    pub(crate) fn shown(state: &State) -> Vec<OptionItem> {
+        Self::ALL
+            .into_iter()
+            .filter(|item| *item != OptionItem::MusicVolume || state.music_available)
+            .collect()
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            OptionItem::Volume => "VOLUME",
+            OptionItem::MusicVolume => "MUSIC",
+            OptionItem::Muted => "SOUND",
+            OptionItem::Theme => "COLORS",
+            OptionItem::Density => "ROCK DENSITY",
+            OptionItem::AlienIndicators => "ALIEN ARROWS",
+            OptionItem::Aliens => "ALIENS",
+            OptionItem::FlightMode => "FLIGHT",
+            OptionItem::StartingLives => "LIVES",
+        }
+    }
+
+    /// Shown but not changeable, see `State::run_settings_locked`
+    pub(crate) fn locked(&self, state: &State) -> bool {
+        let run_setting = matches!(
+            self,
+            OptionItem::Density
+                | OptionItem::Aliens
+                | OptionItem::FlightMode
+                | OptionItem::StartingLives
+        );
+        run_setting && state.run_settings_locked()
+    }
+
+    pub(crate) fn value(&self, state: &State) -> String {
+        let on_off = |on: bool| String::from(if on { "ON" } else { "OFF" });
+        match self {
+            OptionItem::Volume => format!("{}%", (state.volume * 100.0).round()),
+            OptionItem::MusicVolume => format!("{}%", (state.music_volume * 100.0).round()),
+            OptionItem::Muted => on_off(!state.muted),
+            OptionItem::Theme => String::from(state.theme.name()),
+            OptionItem::Density => String::from(state.density.name()),
+            OptionItem::AlienIndicators => on_off(state.alien_indicators),
+            OptionItem::Aliens => on_off(state.aliens_enabled),
+            OptionItem::FlightMode => String::from(state.flight_mode.name()),
+            OptionItem::StartingLives => state.start_lifes.to_string(),
+        }
+    }
+
+    /// One step up with `forward`, otherwise one step down. Toggles flip either way.
+    fn adjust(&self, state: &mut State, forward: bool) {
+        match self {
+            OptionItem::Volume => {
+                let step = if forward { VOLUME_STEP } else { -VOLUME_STEP };
+                state.volume = (state.volume + step).clamp(0.0, 1.0);
+            }
+            OptionItem::MusicVolume => {
+                let step = if forward { VOLUME_STEP } else { -VOLUME_STEP };
+                state.music_volume = (state.music_volume + step).clamp(0.0, 1.0);
+            }
+            OptionItem::Muted => state.muted = !state.muted,
+            OptionItem::Theme => {
+                state.theme = if forward {
+                    state.theme.next()
+                } else {
+                    state.theme.previous()
+                };
+            }
+            OptionItem::Density => {
+                state.density = if forward {
+                    state.density.next()
+                } else {
+                    state.density.previous()
+                };
+                state.density_changed = state.now;
+            }
+            OptionItem::AlienIndicators => state.alien_indicators = !state.alien_indicators,
+            OptionItem::Aliens => {
+                state.aliens_enabled = !state.aliens_enabled;
+                // the ones already around go too, the wave would otherwise wait on them
+                if !state.aliens_enabled {
+                    state.aliens.clear();
+                }
+            }
+            OptionItem::FlightMode => state.flight_mode = state.flight_mode.toggled(),
+            // only counts from the next run on, the current one keeps its lives
+            OptionItem::StartingLives => {
+                state.start_lifes = if forward {
+                    (state.start_lifes + 1).min(MAX_LIFES)
+                } else {
+                    state.start_lifes.saturating_sub(1).max(1)
+                };
+            }
+        }
+    }
+}
+
+/// Up and down pick an option, left and right change it. Changes apply and are saved
+/// right away, so backing out with Escape never loses any.
+pub(crate) fn update_options(state: &mut State) {
+    let items = OptionItem::shown(state);
+    let count = items.len();
+    if is_key_pressed(KeyCode::Up) {
+        state.options_index = (state.options_index + count - 1) % count;
+    }
+    if is_key_pressed(KeyCode::Down) {
+        state.options_index = (state.options_index + 1) % count;
+    }
+
+    let forward = is_key_pressed(KeyCode::Right);
+    let item = items[state.options_index];
+    if (forward || is_key_pressed(KeyCode::Left)) && !item.locked(state) {
+        item.adjust(state, forward);
+        state.save_settings();
+    }
+
+    if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::O) {
+        state.game_state = state.options_return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, settings::Settings, sound::silence};
+
+    #[test]
+    fn the_music_volume_only_shows_with_music_to_play() {
+        let mut state = State::new(silence(), Settings::default(), Config::default(), Some(1));
+        assert!(!OptionItem::shown(&state).contains(&OptionItem::MusicVolume));
+
+        state.music_available = true;
+        assert_eq!(OptionItem::shown(&state).len(), OptionItem::ALL.len());
+    }
+
+    #[test]
+    fn a_daily_challenge_or_a_replay_locks_what_changes_the_run() {
+        let mut state = State::new(silence(), Settings::default(), Config::default(), Some(1));
+        assert!(!OptionItem::Density.locked(&state));
+
+        state.daily = Some(1);
+        assert!(OptionItem::Density.locked(&state));
+        assert!(OptionItem::StartingLives.locked(&state));
+        assert!(!OptionItem::Volume.locked(&state));
+
+        state.daily = None;
+        state.recorded_input = true;
+        assert!(OptionItem::FlightMode.locked(&state));
+    }
+}
@@ -0,0 +1,510 @@
+use ::rand::Rng;
+use macroquad::prelude::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+use crate::{SCALE, SIZE};
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LineParticle {
+    pub(crate) rotation: f32,
+    pub(crate) length: f32,
+    // radians per frame
+    pub(crate) spin: f32,
+}
+
+impl LineParticle {
+    pub fn new(rotation: f32, length: f32) -> Self {
+        Self {
+            rotation,
+            length,
+            spin: 0.0,
+        }
+    }
+}
+
+impl From<LineParticle> for ParticleType {
+    fn from(value: LineParticle) -> Self {
+        ParticleType::Line(value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DotParticle {
+    pub(crate) radius: f32,
+}
+
+impl DotParticle {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+impl From<DotParticle> for ParticleType {
+    fn from(value: DotParticle) -> Self {
+        ParticleType::Dot(value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TextParticle {
+    pub(crate) label: String,
+}
+
+impl TextParticle {
+    pub fn new(label: String) -> Self {
+        Self { label }
+    }
+}
+
+impl From<TextParticle> for ParticleType {
+    fn from(value: TextParticle) -> Self {
+        ParticleType::Text(value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TrailParticle {
+    pub(crate) radius: f32,
+    // initial time to live, the trail fades out relative to it
+    pub(crate) lifetime: f32,
+}
+
+impl TrailParticle {
+    pub fn new(radius: f32, lifetime: f32) -> Self {
+        Self { radius, lifetime }
+    }
+}
+
+impl From<TrailParticle> for ParticleType {
+    fn from(value: TrailParticle) -> Self {
+        ParticleType::Trail(value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ParticleType {
+    Line(LineParticle),
+    Dot(DotParticle),
+    Text(TextParticle),
+    Trail(TrailParticle),
+}
+
+impl ParticleType {
+    /// How much is lost when one of these goes early, trails and dots come in crowds
+    /// while a popup carries information
+    fn importance(&self) -> u8 {
+        match self {
+            ParticleType::Trail(_) => 0,
+            ParticleType::Dot(_) => 1,
+            ParticleType::Line(_) => 2,
+            ParticleType::Text(_) => 3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Particle {
+    pub(crate) position: Vec2,
+    pub(crate) velocity: Vec2,
+    pub(crate) time_to_live: f32,
+    pub(crate) particle_type: ParticleType,
+    // only used with `ParticleColors::Warm`, without one it is drawn in the theme's line color
+    #[serde(with = "crate::save::optional_color")]
+    pub(crate) color: Option<Color>,
+}
+
+/// Particles live in slots that are reused once they expire, so explosions don't keep
+/// growing and shrinking a `Vec`. After a busy moment the number of slots stays put.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct ParticlePool {
+    slots: Vec<Option<Particle>>,
+    // indices of empty slots, filled before `slots` grows
+    free: Vec<usize>,
+    // live particles at most, 0 for no limit. Part of the config, not of the run.
+    #[serde(skip)]
+    limit: usize,
+    // the slot to give up next once at the limit, looked for again only after it's gone.
+    // Time runs down alike for every particle, so it stays the weakest meanwhile.
+    #[serde(skip)]
+    weakest: Option<usize>,
+}
+
+impl ParticlePool {
+    pub(crate) fn with_limit(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Adds `particle`. Once at the limit it takes the place of the least important one
+    /// that has the least time left, or is dropped if it is no more important than that.
+    pub(crate) fn push(&mut self, particle: Particle) {
+        if self.limit > 0 && self.len() >= self.limit {
+            self.weakest = self.weakest.or_else(|| self.find_weakest());
+            let Some(index) = self.weakest else {
+                return;
+            };
+            let weakest = self.slots[index]
+                .as_ref()
+                .map(|weakest| weakest.particle_type.importance());
+            if weakest.is_some_and(|weakest| particle.particle_type.importance() <= weakest) {
+                return;
+            }
+            // the newcomer is worth more, which one goes next is only known after a look
+            self.slots[index] = Some(particle);
+            self.weakest = None;
+            return;
+        }
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(None);
+                self.slots.len() - 1
+            }
+        };
+        let weaker = self
+            .weakest
+            .and_then(|weakest| self.slots[weakest].as_ref())
+            .is_some_and(|weakest| Self::ordering(&particle, weakest).is_lt());
+        if weaker {
+            self.weakest = Some(index);
+        }
+        self.slots[index] = Some(particle);
+    }
+
+    /// Less important first, then the one with less time left
+    fn ordering(a: &Particle, b: &Particle) -> std::cmp::Ordering {
+        a.particle_type
+            .importance()
+            .cmp(&b.particle_type.importance())
+            .then(a.time_to_live.total_cmp(&b.time_to_live))
+    }
+
+    fn find_weakest(&self) -> Option<usize> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|particle| (index, particle)))
+            .min_by(|(_, a), (_, b)| Self::ordering(a, b))
+            .map(|(index, _)| index)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.slots.iter().flatten()
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut Particle> {
+        self.slots.iter_mut().flatten()
+    }
+
+    /// Frees the slot of every particle `keep` returns false for
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&Particle) -> bool) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.as_ref().is_some_and(|particle| !keep(particle)) {
+                *slot = None;
+                self.free.push(index);
+                if self.weakest == Some(index) {
+                    self.weakest = None;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn free_expired(&mut self) {
+        self.retain(|particle| particle.time_to_live > 0.0);
+    }
+
+    /// Number of live particles
+    pub(crate) fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// Number of slots, live or free
+    pub(crate) fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+}
+
+/// Somewhere between red and yellow, like something burning up
+fn warm_color(random: &mut Xoshiro256PlusPlus) -> Color {
+    let heat = random.gen::<f32>();
+    Color::new(1.0, 0.2 + 0.7 * heat, 0.1 * heat, 1.0)
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Star {
+    pub(crate) position: Vec2,
+    // 0.0 is far away, 1.0 is close to the playfield
+    pub(crate) depth: f32,
+}
+
+pub(crate) fn generate_stars(random: &mut Xoshiro256PlusPlus) -> Vec<Star> {
+    const STAR_COUNT: usize = 300;
+
+    (0..STAR_COUNT)
+        .map(|_| Star {
+            position: Vec2::new(random.gen::<f32>() * SIZE.x, random.gen::<f32>() * SIZE.y),
+            depth: random.gen::<f32>(),
+        })
+        .collect()
+}
+
+/// Which way a piece of an explosion flies: anywhere without a `bias`, and bunched up
+/// around it with one, as if the hit carried the pieces along. Both take a single
+/// random number, so an unbiased explosion is the same as it always was.
+fn splat_direction(bias: Option<Vec2>, random: &mut Xoshiro256PlusPlus) -> Vec2 {
+    let turn = random.gen::<f32>();
+    match bias.and_then(Vec2::try_normalize) {
+        Some(bias) => {
+            // cubed, most land close to the bias while a few still go the other way
+            let offset = (turn * 2.0 - 1.0).powi(3) * std::f32::consts::PI;
+            bias.rotate(Vec2::from_angle(offset))
+        }
+        None => Vec2::from_angle(std::f32::consts::TAU * turn),
+    }
+}
+
+pub(crate) fn splat_lines(
+    position: Vec2,
+    count: usize,
+    bias: Option<Vec2>,
+    particles: &mut ParticlePool,
+    random: &mut Xoshiro256PlusPlus,
+) {
+    for _ in 0..count {
+        let direction = splat_direction(bias, random);
+        let position = position + Vec2::new(random.gen::<f32>(), random.gen::<f32>());
+        let velocity = direction * 2.0 * random.gen::<f32>();
+        let time_to_live = 3.0 + random.gen::<f32>();
+        let line_particle = LineParticle::new(
+            std::f32::consts::TAU * random.gen::<f32>(),
+            SCALE * (0.6 + (0.4 * random.gen::<f32>())),
+        );
+        let particle = Particle {
+            position,
+            velocity,
+            time_to_live,
+            particle_type: line_particle.into(),
+            color: Some(warm_color(random)),
+        };
+        particles.push(particle);
+    }
+}
+
+/// Breaks a ship apart along its outline: every edge of `hull` becomes a piece drifting
+/// away from `center` while tumbling. They linger well after the burst of dots is gone.
+pub(crate) fn spawn_debris(
+    hull: &[Vec2],
+    center: Vec2,
+    velocity: Vec2,
+    particles: &mut ParticlePool,
+    random: &mut Xoshiro256PlusPlus,
+) {
+    for (i, &start) in hull.iter().enumerate() {
+        let end = hull[(i + 1) % hull.len()];
+        let edge = end - start;
+        let middle = (start + end) * 0.5;
+        let outwards = (middle - center).try_normalize().unwrap_or(Vec2::X);
+        let mut line = LineParticle::new(edge.y.atan2(edge.x), edge.length());
+        line.spin = (0.02 + 0.04 * random.gen::<f32>()) * random_sign(random);
+        let particle = Particle {
+            position: middle,
+            velocity: velocity * 0.5 + outwards * (0.3 + 0.7 * random.gen::<f32>()),
+            time_to_live: 4.0 + random.gen::<f32>(),
+            particle_type: line.into(),
+            // the ship's own color rather than the fiery one of explosions
+            color: None,
+        };
+        particles.push(particle);
+    }
+}
+
+fn random_sign(random: &mut Xoshiro256PlusPlus) -> f32 {
+    if random.gen::<bool>() {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+pub(crate) fn splat_dots(
+    position: Vec2,
+    count: usize,
+    bias: Option<Vec2>,
+    particles: &mut ParticlePool,
+    random: &mut Xoshiro256PlusPlus,
+) {
+    for _ in 0..count {
+        let direction = splat_direction(bias, random);
+        let position = position + Vec2::new(random.gen::<f32>(), random.gen::<f32>());
+        let velocity = direction * (2.0 + 4.0 * random.gen::<f32>());
+        let time_to_live = 0.5 + (0.4 * random.gen::<f32>());
+        let line_particle = DotParticle::new(SCALE * 0.025);
+        let particle = Particle {
+            position,
+            velocity,
+            time_to_live,
+            particle_type: line_particle.into(),
+            color: Some(warm_color(random)),
+        };
+        particles.push(particle);
+    }
+}
+
+/// Leaves a fading dot behind, the faster the ship the longer it lingers
+pub(crate) fn spawn_trail(position: Vec2, speed: f32, particles: &mut ParticlePool) {
+    let time_to_live = (0.1 + speed * 0.04).min(0.6);
+    let particle = Particle {
+        position,
+        velocity: Vec2::ZERO,
+        time_to_live,
+        particle_type: TrailParticle::new(SCALE * 0.04, time_to_live).into(),
+        color: None,
+    };
+    particles.push(particle);
+}
+
+/// Sprays dots out of the back of a thrusting ship facing `direction`. The stream has the
+/// same density at any frame rate, a frame too short for a whole dot sometimes gets one.
+pub(crate) fn spawn_exhaust(
+    position: Vec2,
+    direction: Vec2,
+    ship_velocity: Vec2,
+    delta: f32,
+    particles: &mut ParticlePool,
+    random: &mut Xoshiro256PlusPlus,
+) {
+    // dots per second
+    const RATE: f32 = 40.0;
+
+    let count = (RATE * delta + random.gen::<f32>()) as usize;
+    for _ in 0..count {
+        let jitter = direction.perp() * (random.gen::<f32>() - 0.5) * 1.5;
+        let velocity = ship_velocity - direction * (2.0 + 2.0 * random.gen::<f32>()) + jitter;
+        let particle = Particle {
+            position,
+            velocity,
+            time_to_live: 0.15 + 0.15 * random.gen::<f32>(),
+            particle_type: DotParticle::new(SCALE * 0.02).into(),
+            color: Some(warm_color(random)),
+        };
+        particles.push(particle);
+    }
+}
+
+pub(crate) fn spawn_score_popup(position: Vec2, value: usize, particles: &mut ParticlePool) {
+    spawn_text_popup(position, value.to_string(), particles);
+}
+
+/// A label rising from `position` and fading out
+pub(crate) fn spawn_text_popup(position: Vec2, label: String, particles: &mut ParticlePool) {
+    let particle = Particle {
+        position,
+        velocity: Vec2::new(0.0, -0.8),
+        time_to_live: 1.0,
+        particle_type: TextParticle::new(label).into(),
+        color: None,
+    };
+    particles.push(particle);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_xoshiro::rand_core::SeedableRng;
+
+    use super::*;
+
+    const DELTA: f32 = 1.0 / 60.0;
+
+    // heavy action, something explodes every frame
+    fn explode(particles: &mut ParticlePool, random: &mut Xoshiro256PlusPlus) {
+        splat_dots(Vec2::ZERO, 10, None, particles, random);
+        splat_lines(Vec2::ZERO, 3, None, particles, random);
+    }
+
+    #[test]
+    fn a_biased_explosion_flies_mostly_along_the_bias() {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(4);
+        let mut pool = ParticlePool::default();
+        splat_dots(Vec2::ZERO, 200, Some(Vec2::X * 3.0), &mut pool, &mut random);
+
+        let along = pool
+            .iter()
+            .filter(|particle| particle.velocity.x > 0.0)
+            .count();
+        assert!(along > 150);
+    }
+
+    #[test]
+    fn reuses_freed_slots() {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(1);
+        let mut pool = ParticlePool::default();
+        splat_dots(Vec2::ZERO, 10, None, &mut pool, &mut random);
+        assert_eq!(pool.len(), 10);
+
+        pool.retain(|_| false);
+        assert_eq!(pool.len(), 0);
+        splat_dots(Vec2::ZERO, 10, None, &mut pool, &mut random);
+        assert_eq!(pool.len(), 10);
+        assert_eq!(pool.capacity(), 10);
+    }
+
+    #[test]
+    fn evicts_the_least_important_particles_at_the_limit() {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(3);
+        let mut pool = ParticlePool::with_limit(20);
+        splat_lines(Vec2::ZERO, 10, None, &mut pool, &mut random);
+        splat_dots(Vec2::ZERO, 10, None, &mut pool, &mut random);
+        splat_lines(Vec2::ZERO, 5, None, &mut pool, &mut random);
+
+        assert_eq!(pool.len(), 20);
+        let lines = pool
+            .iter()
+            .filter(|particle| matches!(particle.particle_type, ParticleType::Line(_)))
+            .count();
+        assert_eq!(lines, 15);
+    }
+
+    #[test]
+    fn drops_what_is_no_more_important_than_the_weakest_at_the_limit() {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(5);
+        let mut pool = ParticlePool::with_limit(10);
+        splat_lines(Vec2::ZERO, 10, None, &mut pool, &mut random);
+        let lived: f32 = pool.iter().map(|particle| particle.time_to_live).sum();
+
+        splat_dots(Vec2::ZERO, 10, None, &mut pool, &mut random);
+        splat_lines(Vec2::ZERO, 10, None, &mut pool, &mut random);
+
+        assert_eq!(pool.len(), 10);
+        // the very same lines, none of the newcomers got in
+        let left: f32 = pool.iter().map(|particle| particle.time_to_live).sum();
+        assert_eq!(left, lived);
+    }
+
+    #[test]
+    fn capacity_stabilizes_under_load() {
+        const FRAMES: usize = 20_000;
+
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(2);
+        let mut pool = ParticlePool::default();
+        let mut capacity_after_warmup = 0;
+        for frame in 0..FRAMES {
+            explode(&mut pool, &mut random);
+            for particle in pool.iter_mut() {
+                particle.time_to_live -= DELTA;
+            }
+            pool.free_expired();
+            if frame == FRAMES / 2 {
+                capacity_after_warmup = pool.capacity();
+            }
+        }
+        // the second half of the run never needed a slot the first half didn't have
+        assert_eq!(pool.capacity(), capacity_after_warmup);
+    }
+}
@@ -0,0 +1,46 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::SCALE;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum PowerUpKind {
+    BulletTime,
+    SpreadShot,
+    Shield,
+    Ricochet,
+    Bomb,
+    RapidFire,
+    // draws the other power-ups and the score popups to the ship for a while
+    Magnet,
+    // the currency of the shop, only dropped while it is turned on
+    Ore,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PowerUp {
+    pub(crate) position: Vec2,
+    pub(crate) time_to_live: f32,
+    pub(crate) kind: PowerUpKind,
+    // only ever set by a magnet, a dropped power-up stays where it is
+    #[serde(default)]
+    pub(crate) velocity: Vec2,
+}
+
+impl PowerUp {
+    // seconds a dropped power-up waits to be collected
+    pub(crate) const LIFETIME: f32 = 8.0;
+
+    pub(crate) fn new(position: Vec2, kind: PowerUpKind) -> Self {
+        Self {
+            position,
+            time_to_live: Self::LIFETIME,
+            kind,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    pub(crate) fn collision_size(&self) -> f32 {
+        SCALE * 0.6
+    }
+}
@@ -0,0 +1,114 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::SCALE;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Owner {
+    // index into `State::players`
+    Player(usize),
+    Alien,
+}
+
+impl Owner {
+    pub(crate) fn is_player(&self) -> bool {
+        matches!(self, Owner::Player(_))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ProjectileKind {
+    Straight,
+    // slowly turns towards the ship
+    Homing,
+    // released after holding fire, bigger and faster
+    Charged,
+    // bounces off the edges of the playfield instead of wrapping, dies on the last one
+    Ricochet { bounces: u32 },
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Projectile {
+    pub(crate) position: Vec2,
+    // where it was a frame ago, the streak behind it is drawn from there
+    pub(crate) previous_position: Vec2,
+    pub(crate) velocity: Vec2,
+    pub(crate) state: ProjectileState,
+    pub(crate) owner: Owner,
+    pub(crate) kind: ProjectileKind,
+    // how many more targets it passes through before it is used up
+    pub(crate) pierce: u32,
+    // struck something already, a piercing shot counts only once for the accuracy
+    pub(crate) landed: bool,
+    // rocks it destroyed and the sum of where they were, several make a chain
+    pub(crate) kills: u32,
+    pub(crate) kill_positions: Vec2,
+}
+
+impl Projectile {
+    // edges a ricochet shot bounces off before it is used up
+    pub(crate) const RICOCHET_BOUNCES: u32 = 3;
+
+    pub(crate) fn is_alive(self: &Self) -> bool {
+        let state = &self.state;
+        state.into()
+    }
+
+    pub(crate) fn radius(&self) -> f32 {
+        match self.kind {
+            ProjectileKind::Straight | ProjectileKind::Ricochet { .. } => (SCALE * 0.05).max(1.0),
+            ProjectileKind::Homing => (SCALE * 0.1).max(2.0),
+            ProjectileKind::Charged => (SCALE * 0.15).max(3.0),
+        }
+    }
+
+    /// The stretch it covered in the last frame, nothing if it wrapped around an edge
+    /// since the line would then cut across the whole playfield
+    pub(crate) fn streak(&self) -> Option<(Vec2, Vec2)> {
+        let covered = self.position - self.previous_position;
+        if covered.length() > self.velocity.length() * 2.0 {
+            return None;
+        }
+        Some((self.previous_position, self.position))
+    }
+
+    /// Called when it hits something, piercing projectiles keep going while they can.
+    /// True only for the first thing it hits.
+    pub(crate) fn hit(&mut self) -> bool {
+        let first = !self.landed;
+        self.landed = true;
+        if self.pierce > 0 {
+            self.pierce -= 1;
+        } else {
+            self.state = ProjectileState::Dead;
+        }
+        first
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ProjectileState {
+    Alive { time_to_live: f32 },
+    Dead,
+}
+
+impl From<f32> for ProjectileState {
+    fn from(value: f32) -> Self {
+        if value > 0.0 {
+            Self::Alive {
+                time_to_live: value,
+            }
+        } else {
+            Self::Dead
+        }
+    }
+}
+
+impl From<&ProjectileState> for bool {
+    fn from(value: &ProjectileState) -> Self {
+        match value {
+            ProjectileState::Dead => false,
+            ProjectileState::Alive { time_to_live } => time_to_live > &0.0,
+        }
+    }
+}
@@ -0,0 +1,1327 @@
+use std::time::{Duration, Instant};
+
+use macroquad::prelude::*;
+
+use crate::{
+    achievement::TOAST_TIME,
+    alien::AlienSize,
+    config::{ParticleColors, WrapMode, VERSION},
+    daily,
+    font::glyph,
+    game::{alien_aggression, playfield_delta, GameState, RunMode, State},
+    gravity::GravityWell,
+    grid::SpatialGrid,
+    options::OptionItem,
+    particle::ParticleType,
+    powerup::PowerUpKind,
+    projectile::{Owner, ProjectileKind},
+    rock::{collision_cell_size, rock_outline, Rock},
+    ship::{Ship, SHIP_POINTS},
+    shop::ShopItem,
+    theme::{with_alpha, Theme},
+    SCALE, SIZE, THICKNESS,
+};
+
+/// Factor the logical playfield is scaled by into the window. The configured
+/// `render_scale`, or 0 for the largest one still fitting the window.
+pub(crate) fn playfield_scale(render_scale: f32) -> f32 {
+    let fit = (screen_width() / SIZE.x).min(screen_height() / SIZE.y);
+    if render_scale > 0.0 {
+        render_scale.min(fit)
+    } else {
+        fit
+    }
+}
+
+/// Top left corner of the letterboxed playfield in window pixels
+pub(crate) fn playfield_origin(render_scale: f32) -> Vec2 {
+    (Vec2::new(screen_width(), screen_height()) - SIZE * playfield_scale(render_scale)) * 0.5
+}
+
+/// Camera mapping the logical playfield (`SIZE`) into the window, the remaining
+/// space on the sides is left black instead of stretching the playfield. Only the
+/// drawing is scaled, everything in the game keeps its logical coordinates.
+fn playfield_camera(offset: Vec2, render_scale: f32) -> Camera2D {
+    let origin = playfield_origin(render_scale);
+    let size = SIZE * playfield_scale(render_scale);
+    Camera2D {
+        target: SIZE * 0.5 + offset,
+        // a positive y zoom keeps the y-down orientation of the default camera
+        zoom: Vec2::new(2.0 / SIZE.x, 2.0 / SIZE.y),
+        viewport: Some((
+            origin.x as i32,
+            origin.y as i32,
+            size.x as i32,
+            size.y as i32,
+        )),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn render(state: &State) {
+    // The playfield is shaken, the HUD below is not so it stays readable
+    set_camera(&playfield_camera(
+        state.shake_offset,
+        state.config.render_scale,
+    ));
+    let theme = state.theme.colors();
+
+    for star in state.stars.iter() {
+        let alpha = 0.15 + 0.35 * star.depth;
+        draw_circle_vec2(
+            star.position,
+            0.5 + star.depth,
+            with_alpha(theme.line, alpha),
+        );
+    }
+
+    render_ghost(state, theme.line);
+
+    // timed for the debug overlay, where the cost of the glow shows
+    let outlines_start = Instant::now();
+    let glow = state.config.phosphor_glow;
+    for player in state.players.iter() {
+        let ship = &player.ship;
+        if (&ship.status).into() {
+            // blinks while invulnerable
+            if state.now >= ship.invulnerable_until || (state.now * 10.0) as i32 % 2 == 0 {
+                draw_glowing_outline(&ship.hull(), true, theme.line, glow);
+            }
+            if state.config.assist_arrow {
+                render_assist_arrow(state, ship.position, theme.line);
+            }
+            if state.config.aim_line {
+                render_aim_line(state, ship, theme.line);
+            }
+            // a faint ring pulsing outwards while a magnet pulls things in
+            if state.now < state.magnet_until {
+                let wave = (state.now * 1.5).fract();
+                let color = with_alpha(theme.power_up, 0.4 * (1.0 - wave));
+                let radius = SCALE * (2.0 - 1.2 * wave);
+                draw_circle_lines(ship.position.x, ship.position.y, radius, 1.0, color);
+            }
+            if ship.shielded {
+                let alpha = 0.6 + 0.4 * (state.now * 6.0).sin();
+                let color = with_alpha(theme.line, alpha);
+                let position = ship.position;
+                draw_circle_lines(position.x, position.y, SCALE * 0.8, THICKNESS, color);
+            }
+            // the charge grows as a dot at the nose until the shot is ready
+            if ship.charge > 0.15 {
+                let charge = (ship.charge / Ship::FULL_CHARGE).min(1.0);
+                let nose = Vec2::from_angle(ship.rotation + std::f32::consts::PI * 0.5);
+                draw_circle_vec2(
+                    ship.position + nose * SCALE * 0.6,
+                    SCALE * 0.15 * charge,
+                    theme.line,
+                );
+            }
+            if player.render_thruster_plume {
+                // behind the thrust, which the twin-stick controls decouple from the facing
+                let direction = player.thrust_direction;
+                let rotation = direction.y.atan2(direction.x) - std::f32::consts::PI * 0.5;
+                let thruster_points = [
+                    Vec2::new(-0.3, -0.4),
+                    Vec2::new(0.0, -1.0),
+                    Vec2::new(0.3, -0.4),
+                ];
+
+                draw_glowing_lines(
+                    ship.position,
+                    SCALE,
+                    rotation,
+                    &thruster_points,
+                    true,
+                    theme.line,
+                    glow,
+                );
+            }
+        }
+    }
+
+    if let Some(well) = &state.gravity_well {
+        draw_gravity_well(well.position, state.now, theme.line);
+    }
+
+    let rock_color = theme.rock_color(state.wave);
+    for rock in state.rocks.iter() {
+        let color = if rock.is_armored() {
+            theme.armored
+        } else {
+            rock_color
+        };
+        draw_space_rock(rock, rock.intro_scale(state.now), color, glow);
+    }
+
+    let aggression = alien_aggression(state);
+    for alien in state.aliens.iter() {
+        draw_alien(alien.position, &alien.size, theme.alien, glow);
+        if !state.config.alien_telegraph {
+            continue;
+        }
+        if let Some(wind_up) = alien.shot_wind_up(state.now, aggression) {
+            let radius = alien.radius() * 0.3 * wind_up;
+            draw_circle_vec2(alien.position, radius, with_alpha(theme.alien, 0.7));
+        }
+    }
+
+    for power_up in state.power_ups.iter() {
+        // blinks during its last seconds to warn that it's about to vanish
+        const BLINK_TIME: f32 = 2.0;
+        if power_up.time_to_live > BLINK_TIME || (power_up.time_to_live * 8.0) as i32 % 2 == 0 {
+            draw_power_up(power_up.position, power_up.kind, theme.power_up, glow);
+        }
+    }
+    let outlines_time = outlines_start.elapsed();
+
+    let line_points = [Vec2::new(-0.5, 0.0), Vec2::new(0.5, 0.0)];
+
+    for particle in state.particles.iter() {
+        let color = match (state.config.particle_colors, particle.color) {
+            (ParticleColors::Warm, Some(color)) => color,
+            _ => theme.line,
+        };
+        match &particle.particle_type {
+            ParticleType::Line(line) => draw_outline(
+                &transform_points(particle.position, line.length, line.rotation, &line_points),
+                true,
+                color,
+            ),
+            ParticleType::Dot(dot) => draw_circle_vec2(particle.position, dot.radius, color),
+            ParticleType::Trail(trail) => {
+                let alpha = (particle.time_to_live / trail.lifetime).clamp(0.0, 1.0);
+                draw_circle_vec2(particle.position, trail.radius, with_alpha(color, alpha));
+            }
+            ParticleType::Text(text) => {
+                const FONT_SIZE: f32 = 24.0;
+                let label = &text.label;
+                let dimensions = measure_text(label, None, FONT_SIZE as u16, 1.0);
+                // fades out over the last second of its life
+                let alpha = particle.time_to_live.clamp(0.0, 1.0);
+                let color = with_alpha(color, alpha);
+                draw_text(
+                    label,
+                    particle.position.x - dimensions.width * 0.5,
+                    particle.position.y,
+                    FONT_SIZE,
+                    color,
+                );
+            }
+        };
+    }
+
+    for projectile in state.projectiles.iter() {
+        // a motion blur behind each shot, incoming fire gets a longer one in the alien color
+        if let Some((from, to)) = projectile.streak() {
+            let (from, color) = match projectile.owner {
+                Owner::Player(_) => (from, theme.projectile),
+                Owner::Alien => (to + (from - to) * 2.0, theme.alien),
+            };
+            draw_line_vec2(from, to, THICKNESS * 0.5, with_alpha(color, 0.5));
+        }
+        let color = match projectile.owner {
+            Owner::Player(_) => theme.projectile,
+            Owner::Alien => theme.alien_projectile,
+        };
+        match projectile.kind {
+            ProjectileKind::Straight if projectile.owner == Owner::Alien => {
+                // drawn a bit larger than it hits, with a ring when the theme gives it
+                // no color of its own
+                let position = projectile.position;
+                let radius = projectile.radius() * 1.6;
+                draw_circle_vec2(position, radius, color);
+                if theme.alien_projectile == theme.projectile {
+                    let ring = radius * 2.0;
+                    draw_circle_lines(position.x, position.y, ring, THICKNESS * 0.4, color);
+                }
+            }
+            ProjectileKind::Straight => {
+                draw_circle_vec2(projectile.position, projectile.radius(), color)
+            }
+            ProjectileKind::Homing => {
+                // bigger and with a short tail so it stands out from regular shots
+                let tail =
+                    projectile.position - projectile.velocity.normalize_or_zero() * SCALE * 0.4;
+                draw_line_vec2(projectile.position, tail, THICKNESS * 0.5, color);
+                draw_circle_vec2(projectile.position, projectile.radius(), color)
+            }
+            ProjectileKind::Charged => {
+                draw_circle_vec2(projectile.position, projectile.radius(), color)
+            }
+            ProjectileKind::Ricochet { .. } => {
+                // a ring around the dot, it doesn't wrap like the others
+                let position = projectile.position;
+                draw_circle_vec2(position, projectile.radius(), color);
+                draw_circle_lines(
+                    position.x,
+                    position.y,
+                    projectile.radius() * 3.0,
+                    THICKNESS * 0.5,
+                    color,
+                );
+            }
+        }
+    }
+
+    if state.last_stand > 0.0 {
+        let tint = with_alpha(theme.danger, 0.18 * state.last_stand);
+        draw_rectangle(0.0, 0.0, SIZE.x, SIZE.y, tint);
+    }
+
+    if state.debug && state.debug_heatmap {
+        render_rock_heatmap(state);
+    }
+    if state.debug && state.debug_hitboxes {
+        render_hitboxes(state);
+    }
+    if state.debug && state.debug_velocities {
+        render_velocities(state);
+    }
+
+    set_camera(&playfield_camera(Vec2::ZERO, state.config.render_scale));
+
+    // drawn still, so the frame doesn't shake along with what's inside it
+    if state.config.vignette {
+        render_vignette(theme);
+    }
+
+    if state.alien_indicators && (&state.ship().status).into() {
+        render_alien_indicators(state);
+    }
+
+    // an endless run has no lives to show
+    let life_rows = if state.run_mode == RunMode::Endless {
+        0
+    } else if state.shares_lifes() {
+        render_lifes(state.lifes, SCALE, theme.line);
+        1
+    } else {
+        // one row per player
+        for (index, player) in state.players.iter().enumerate() {
+            render_lifes(player.lifes, SCALE + index as f32 * SCALE * 1.2, theme.line);
+        }
+        state.players.len()
+    };
+    // and the bombs of each player below
+    for (index, player) in state.players.iter().enumerate() {
+        let y = SCALE * (0.4 + life_rows as f32 * 1.2 + index as f32 * 0.6);
+        render_bombs(player.bombs, y, theme.line);
+    }
+
+    // Render Score
+    draw_number(state.score, Vec2::new(SIZE.x - SCALE, SCALE), theme.line);
+    draw_time(
+        state.now - state.run_start,
+        Vec2::new(SIZE.x * 0.5, SCALE),
+        theme.line,
+    );
+    if state.combo.multiplier > 1 {
+        draw_multiplier(
+            state.combo.multiplier,
+            Vec2::new(SIZE.x - SCALE, SCALE * 2.2),
+            theme.line,
+        );
+    }
+
+    if state.config.shop {
+        let label = format!("ORE {}", state.credits);
+        let scale = SCALE * 0.3;
+        let x = SIZE.x - SCALE * 0.5 - vector_text_width(&label, scale) + scale * 0.5;
+        draw_vector_text(&label, Vec2::new(x, SCALE * 3.2), scale, theme.power_up);
+    }
+
+    render_wave_banner(state);
+    render_density_banner(state);
+    render_achievement_toast(state);
+
+    if state.attract {
+        draw_centered_title("DEMO", SIZE.y * 0.8, theme.line);
+        draw_centered_text(
+            "press any key",
+            HINT_SIZE,
+            SIZE.y * 0.8 + TITLE_SIZE,
+            theme.line,
+        );
+    }
+
+    if state.sandbox {
+        draw_centered_text(
+            "SANDBOX   1-4 rocks   5-6 aliens   BACKSPACE clears",
+            HINT_SIZE,
+            SIZE.y - SCALE * 0.5,
+            theme.line,
+        );
+    }
+
+    if state.zen {
+        draw_centered_text("ZEN", HINT_SIZE, SIZE.y - SCALE * 0.5, theme.line);
+    } else if state.run_mode == RunMode::Endless && !state.sandbox {
+        draw_centered_text("ENDLESS", HINT_SIZE, SIZE.y - SCALE * 0.5, theme.line);
+    } else if let Some(day) = state.daily {
+        let label = format!("DAILY {}", daily::date(day));
+        draw_centered_text(&label, HINT_SIZE, SIZE.y - SCALE * 0.5, theme.line);
+    }
+
+    if state.debug {
+        render_debug_overlay(state, outlines_time);
+    }
+
+    match state.game_state {
+        GameState::Menu => render_menu_overlay(state),
+        GameState::Paused => render_pause_overlay(state),
+        GameState::GameOver => render_game_over_overlay(state),
+        GameState::Options => render_options_overlay(state),
+        GameState::Shop => render_shop_overlay(state),
+        GameState::Playing => {}
+    }
+
+    // on top of the pause screen as well, the first press of a restart
+    if state.restart_armed > 0.0 {
+        let hint = format!(
+            "press {:?} again to restart",
+            state.config.key_bindings.restart.0
+        );
+        draw_centered_text(&hint, HINT_SIZE, SIZE.y - SCALE * 1.2, theme.danger);
+    }
+
+    set_default_camera();
+}
+
+/// A short faint line from the ship toward the closest rock or alien, the shortest way
+/// over the edges. Nothing between waves when the field is empty.
+fn render_assist_arrow(state: &State, origin: Vec2, color: Color) {
+    let wrap_mode = state.config.wrap_mode;
+    let nearest = state
+        .rocks
+        .iter()
+        .map(|rock| rock.position)
+        .chain(state.aliens.iter().map(|alien| alien.position))
+        .map(|position| playfield_delta(wrap_mode, origin, position))
+        .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+    let Some(delta) = nearest else {
+        return;
+    };
+    let direction = delta.normalize_or_zero();
+    let start = origin + direction * SCALE * 1.2;
+    let end = origin + direction * SCALE * 2.2;
+    let color = with_alpha(color, 0.3);
+    draw_line_vec2(start, end, THICKNESS, color);
+    // the head
+    for side in [-0.5_f32, 0.5] {
+        let back = Vec2::from_angle(std::f32::consts::PI + side).rotate(direction);
+        draw_line_vec2(end, end + back * SCALE * 0.3, THICKNESS, color);
+    }
+}
+
+/// Where a regular shot fired right now would go, over the edges if the playfield wraps
+fn render_aim_line(state: &State, ship: &Ship, color: Color) {
+    // shots move every frame, the game is tuned for 60 of them a second
+    const FRAMES_PER_SECOND: f32 = 60.0;
+
+    let direction = Vec2::from_angle(ship.rotation + std::f32::consts::PI * 0.5);
+    let mut position = ship.position + direction * SCALE * 0.55;
+    let config = &state.config;
+    let mut remaining = config.shot_speed
+        * FRAMES_PER_SECOND
+        * state.weapon.projectile_lifetime(config.shot_lifetime);
+    let color = with_alpha(color, 0.2);
+    // every piece ends at an edge, after a few the line is long enough
+    for _ in 0..4 {
+        let to_edge = distance_to_edge(position, direction);
+        let length = remaining.min(to_edge);
+        let end = position + direction * length;
+        draw_line_vec2(position, end, THICKNESS * 0.4, color);
+        remaining -= length;
+        if remaining <= 0.0 || state.config.wrap_mode == WrapMode::Bounce {
+            break;
+        }
+        position = Vec2::new(end.x.rem_euclid(SIZE.x), end.y.rem_euclid(SIZE.y));
+    }
+}
+
+/// How far `position` is from leaving the playfield along `direction`
+fn distance_to_edge(position: Vec2, direction: Vec2) -> f32 {
+    let mut distance = f32::MAX;
+    if direction.x > 0.0 {
+        distance = distance.min((SIZE.x - position.x) / direction.x);
+    } else if direction.x < 0.0 {
+        distance = distance.min(-position.x / direction.x);
+    }
+    if direction.y > 0.0 {
+        distance = distance.min((SIZE.y - position.y) / direction.y);
+    } else if direction.y < 0.0 {
+        distance = distance.min(-position.y / direction.y);
+    }
+    distance.max(0.0)
+}
+
+/// Small arrows on the playfield's edge in the direction of aliens far away from the ship
+fn render_alien_indicators(state: &State) {
+    const MIN_DISTANCE: f32 = SCALE * 8.0;
+    const MARGIN: f32 = SCALE * 0.5;
+    const ARROW: [Vec2; 3] = [
+        Vec2::new(-0.5, -0.5),
+        Vec2::new(0.0, 0.5),
+        Vec2::new(0.5, -0.5),
+    ];
+    let color = with_alpha(state.theme.colors().line, 0.5);
+
+    let origin = state.ship().position;
+    for alien in state.aliens.iter() {
+        // the shortest way, which may lead over the edge the alien will wrap in from
+        let delta = playfield_delta(state.config.wrap_mode, origin, alien.position);
+        if delta.length() < MIN_DISTANCE {
+            continue;
+        }
+        let direction = delta.normalize();
+
+        // walk from the ship along the direction until the first inset edge is reached
+        let mut reach = f32::MAX;
+        if direction.x != 0.0 {
+            let edge = if direction.x > 0.0 {
+                SIZE.x - MARGIN
+            } else {
+                MARGIN
+            };
+            reach = reach.min((edge - origin.x) / direction.x);
+        }
+        if direction.y != 0.0 {
+            let edge = if direction.y > 0.0 {
+                SIZE.y - MARGIN
+            } else {
+                MARGIN
+            };
+            reach = reach.min((edge - origin.y) / direction.y);
+        }
+        let position = origin + direction * reach.max(0.0);
+
+        // the arrow points along +y in model space
+        let rotation = direction.y.atan2(direction.x) - std::f32::consts::PI * 0.5;
+        let points = transform_points(position, SCALE * 0.4, rotation, &ARROW);
+        for pair in points.windows(2) {
+            draw_line_vec2(pair[0], pair[1], THICKNESS, color);
+        }
+    }
+}
+
+/// Names the density picked with N for a moment, it shows with the next field
+fn render_density_banner(state: &State) {
+    const BANNER_TIME: f32 = 2.0;
+
+    if state.now - state.density_changed > BANNER_TIME {
+        return;
+    }
+    let text = format!("DENSITY {} FROM THE NEXT WAVE", state.density.name());
+    draw_centered_text(&text, HINT_SIZE, SIZE.y * 0.2, state.theme.colors().line);
+}
+
+/// "WAVE N" for a moment after a wave started, drawn over the running game
+fn render_wave_banner(state: &State) {
+    const BANNER_TIME: f32 = 2.0;
+
+    let shown = state.now - state.stage_start;
+    if shown > BANNER_TIME || !matches!(state.game_state, GameState::Playing | GameState::Paused) {
+        return;
+    }
+    let theme = state.theme.colors();
+    draw_centered_title("WAVE", SIZE.y * 0.3, theme.line);
+    draw_centered_number(state.wave, SIZE.y * 0.3 + SCALE * 1.2, theme.line);
+}
+
+/// The latest unlocked achievement in a box below the clock, fading out at the end
+fn render_achievement_toast(state: &State) {
+    const FADE: f32 = 0.5;
+
+    let Some((achievement, shown)) = state.toasts.iter().find(|(_, shown)| *shown <= state.now)
+    else {
+        return;
+    };
+    let theme = state.theme.colors();
+    let alpha = ((shown + TOAST_TIME - state.now) / FADE).clamp(0.0, 1.0);
+    let scale = SCALE * 0.3;
+    let width = vector_text_width(achievement.name(), scale) + SCALE;
+    let center = Vec2::new(SIZE.x * 0.5, SCALE * 2.4);
+    draw_rectangle_lines(
+        center.x - width * 0.5,
+        center.y - SCALE * 0.6,
+        width,
+        SCALE * 1.2,
+        THICKNESS,
+        with_alpha(theme.line, alpha),
+    );
+    draw_centered_text(
+        "ACHIEVEMENT",
+        HINT_SIZE,
+        center.y - SCALE * 0.2,
+        with_alpha(theme.line, alpha),
+    );
+    let x = (SIZE.x - vector_text_width(achievement.name(), scale) + scale) * 0.5;
+    draw_vector_text(
+        achievement.name(),
+        Vec2::new(x, center.y + SCALE * 0.25),
+        scale,
+        with_alpha(theme.line, alpha),
+    );
+}
+
+fn render_lifes(lifes: usize, y: f32, color: Color) {
+    // one icon per life up to this many, a single icon and a counter beyond
+    const MAX_LIFE_ICONS: usize = 5;
+
+    let icons = if lifes > MAX_LIFE_ICONS { 1 } else { lifes };
+    for life in 0..icons {
+        draw_lines(
+            Vec2::new(SCALE + life as f32 * SCALE, y),
+            SCALE,
+            -std::f32::consts::PI,
+            &SHIP_POINTS,
+            true,
+            color,
+        );
+    }
+
+    if lifes > MAX_LIFE_ICONS {
+        let digits = lifes.to_string().len() as f32;
+        draw_multiplier(lifes, Vec2::new(SCALE * (2.0 + digits), y), color);
+    }
+}
+
+fn render_bombs(bombs: usize, y: f32, color: Color) {
+    for bomb in 0..bombs {
+        draw_circle_lines(
+            SCALE + bomb as f32 * SCALE * 0.6,
+            y,
+            SCALE * 0.15,
+            THICKNESS * 0.6,
+            color,
+        );
+    }
+}
+
+const TITLE_SIZE: f32 = 64.0;
+const HINT_SIZE: f32 = 24.0;
+
+fn render_pause_overlay(state: &State) {
+    let theme = state.theme.colors();
+    dim_playfield(theme);
+    draw_centered_title("PAUSED", SIZE.y * 0.5, theme.line);
+    let resume = format!(
+        "press {:?} to resume, O for the options",
+        state.config.key_bindings.pause.0
+    );
+    draw_centered_text(&resume, HINT_SIZE, SIZE.y * 0.5 + TITLE_SIZE, theme.line);
+    if state.can_leave() {
+        let leave = if state.sandbox {
+            "Q to leave the sandbox"
+        } else if state.zen {
+            "Q to leave zen mode"
+        } else {
+            "Q to end the run"
+        };
+        draw_centered_text(
+            leave,
+            HINT_SIZE,
+            SIZE.y * 0.5 + TITLE_SIZE + HINT_SIZE * 1.5,
+            theme.line,
+        );
+    }
+}
+
+fn render_menu_overlay(state: &State) {
+    let theme = state.theme.colors();
+    dim_playfield(theme);
+    draw_centered_title(&state.config.title, SIZE.y * 0.4, theme.line);
+    draw_centered_text(
+        "press SPACE to start",
+        HINT_SIZE,
+        SIZE.y * 0.4 + TITLE_SIZE,
+        theme.line,
+    );
+    draw_centered_text(
+        "E for an endless run, S for the sandbox, Z for zen mode, O for the options",
+        HINT_SIZE,
+        SIZE.y * 0.4 + TITLE_SIZE + HINT_SIZE * 1.5,
+        theme.line,
+    );
+    draw_centered_text(
+        "D for the daily challenge, the same for everyone today",
+        HINT_SIZE,
+        SIZE.y * 0.4 + TITLE_SIZE + HINT_SIZE * 3.0,
+        theme.line,
+    );
+    if state.high_score > 0 {
+        draw_centered_text("HIGH SCORE", HINT_SIZE, SIZE.y * 0.6, theme.line);
+        draw_centered_number(state.high_score, SIZE.y * 0.6 + SCALE, theme.line);
+    }
+    if state.endless_high_score > 0 {
+        draw_centered_text("ENDLESS BEST", HINT_SIZE, SIZE.y * 0.72, theme.line);
+        draw_centered_number(state.endless_high_score, SIZE.y * 0.72 + SCALE, theme.line);
+    }
+    if state.config.show_version {
+        // bottom right corner, small enough not to draw attention
+        let scale = SCALE * 0.3;
+        let version = format!("V{}", VERSION);
+        let x = SIZE.x - SCALE * 0.5 - vector_text_width(&version, scale) + scale * 0.5;
+        let color = with_alpha(theme.line, 0.5);
+        draw_vector_text(&version, Vec2::new(x, SIZE.y - SCALE * 0.5), scale, color);
+    }
+}
+
+fn render_options_overlay(state: &State) {
+    const LINE_HEIGHT: f32 = HINT_SIZE * 1.6;
+
+    let theme = state.theme.colors();
+    dim_playfield(theme);
+    draw_centered_title("OPTIONS", SIZE.y * 0.3, theme.line);
+    for (index, item) in OptionItem::shown(state).iter().enumerate() {
+        let y = SIZE.y * 0.4 + index as f32 * LINE_HEIGHT;
+        if index == state.options_index {
+            draw_text(">", SIZE.x * 0.28, y, HINT_SIZE, theme.line);
+        }
+        draw_text(item.label(), SIZE.x * 0.31, y, HINT_SIZE, theme.line);
+        // locked ones are dimmed, they can't be changed right now
+        let color = if item.locked(state) {
+            with_alpha(theme.line, 0.4)
+        } else {
+            theme.line
+        };
+        draw_text(&item.value(state), SIZE.x * 0.6, y, HINT_SIZE, color);
+    }
+    draw_centered_text(
+        "UP/DOWN to pick, LEFT/RIGHT to change, ESC to go back",
+        HINT_SIZE,
+        SIZE.y * 0.8,
+        theme.line,
+    );
+}
+
+fn render_shop_overlay(state: &State) {
+    const LINE_HEIGHT: f32 = HINT_SIZE * 1.6;
+
+    let theme = state.theme.colors();
+    dim_playfield(theme);
+    draw_centered_title("SHOP", SIZE.y * 0.25, theme.line);
+    let credits = format!("{} ORE", state.credits);
+    let scale = SCALE * 0.4;
+    let x = (SIZE.x - vector_text_width(&credits, scale) + scale) * 0.5;
+    draw_vector_text(&credits, Vec2::new(x, SIZE.y * 0.33), scale, theme.power_up);
+    for (index, item) in ShopItem::ALL.iter().enumerate() {
+        let y = SIZE.y * 0.42 + index as f32 * LINE_HEIGHT;
+        if index == state.shop_index {
+            draw_text(">", SIZE.x * 0.28, y, HINT_SIZE, theme.line);
+        }
+        draw_text(item.label(), SIZE.x * 0.31, y, HINT_SIZE, theme.line);
+        let price = match (item, item.price(state)) {
+            (ShopItem::Leave, _) => String::new(),
+            (_, Some(price)) => format!("{} ORE", price),
+            (_, None) => String::from("SOLD OUT"),
+        };
+        draw_text(&price, SIZE.x * 0.6, y, HINT_SIZE, theme.line);
+    }
+    draw_centered_text(state.shop_message, HINT_SIZE, SIZE.y * 0.72, theme.line);
+    draw_centered_text(
+        "UP/DOWN to pick, ENTER to buy, ESC to skip to the next wave",
+        HINT_SIZE,
+        SIZE.y * 0.8,
+        theme.line,
+    );
+}
+
+fn render_game_over_overlay(state: &State) {
+    let theme = state.theme.colors();
+    dim_playfield(theme);
+    draw_centered_title("GAME OVER", SIZE.y * 0.35, theme.line);
+    draw_centered_number(state.score, SIZE.y * 0.35 + SCALE * 1.5, theme.line);
+    let high_score = match (state.new_high_score, state.daily.is_some()) {
+        (true, true) => "NEW DAILY HIGH SCORE",
+        (true, false) => "NEW HIGH SCORE",
+        (false, true) => "DAILY HIGH SCORE",
+        (false, false) => "HIGH SCORE",
+    };
+    draw_centered_text(high_score, HINT_SIZE, SIZE.y * 0.55, theme.line);
+    draw_centered_number(state.best_score(), SIZE.y * 0.55 + SCALE, theme.line);
+    let hint = if state.continue_left > 0.0 {
+        draw_centered_text("CONTINUE?", HINT_SIZE, SIZE.y * 0.68, theme.line);
+        draw_centered_number(
+            state.continue_left.ceil() as usize,
+            SIZE.y * 0.68 + SCALE,
+            theme.line,
+        );
+        "SPACE to continue, ESC for the menu"
+    } else {
+        "SPACE to restart, ESC for the menu"
+    };
+    let hint = if state.continue_left > 0.0 {
+        format!(
+            "{}, {:?} to start over",
+            hint, state.config.key_bindings.restart.0
+        )
+    } else {
+        hint.to_string()
+    };
+    draw_centered_text(&hint, HINT_SIZE, SIZE.y * 0.82, theme.line);
+    render_run_stats(state);
+}
+
+/// The totals of the run in a column to the left of the score
+fn render_run_stats(state: &State) {
+    const LINE_HEIGHT: f32 = HINT_SIZE * 1.3;
+
+    let stats = &state.stats;
+    let [boss, big, medium, small] = stats.rocks_destroyed;
+    let [big_aliens, small_aliens] = stats.aliens_destroyed;
+    let accuracy = match stats.accuracy() {
+        Some(accuracy) => format!("{}%", (accuracy * 100.0).round()),
+        None => String::from("-"),
+    };
+    let streak = stats.longest_streak as u32;
+    let lines = [
+        format!("WAVE {}", state.wave),
+        format!("ROCKS {}", boss + big + medium + small),
+        format!("  BOSS {} BIG {} MED {} SMALL {}", boss, big, medium, small),
+        format!("  LOST TO OTHERS {}", stats.rocks_lost),
+        format!("ALIENS {}", big_aliens + small_aliens),
+        format!("  BIG {} SMALL {}", big_aliens, small_aliens),
+        format!("SHOTS {}", stats.shots_fired),
+        format!("ACCURACY {}", accuracy),
+        format!("LONGEST STREAK {}:{:02}", streak / 60, streak % 60),
+    ];
+    let color = with_alpha(state.theme.colors().line, 0.8);
+    for (index, line) in lines.iter().enumerate() {
+        let y = SIZE.y * 0.3 + index as f32 * LINE_HEIGHT;
+        draw_text(line, SCALE, y, HINT_SIZE, color);
+    }
+}
+
+/// Darkens the frozen playfield so overlays read clearly
+fn dim_playfield(theme: &Theme) {
+    draw_rectangle(0.0, 0.0, SIZE.x, SIZE.y, with_alpha(theme.background, 0.5));
+}
+
+/// Bands of the background color fading in towards the edges, with a faint line around
+fn render_vignette(theme: &Theme) {
+    const BANDS: usize = 8;
+    let band = SCALE * 0.25;
+    for index in 0..BANDS {
+        let inset = index as f32 * band;
+        let alpha = 0.12 * (1.0 - index as f32 / BANDS as f32);
+        let color = with_alpha(theme.background, alpha);
+        let (width, height) = (SIZE.x - inset * 2.0, SIZE.y - inset * 2.0);
+        draw_rectangle_lines(inset, inset, width, height, band * 2.0, color);
+    }
+    let border = with_alpha(theme.line, 0.25);
+    draw_rectangle_lines(0.0, 0.0, SIZE.x, SIZE.y, THICKNESS, border);
+}
+
+/// Big text in the line-art font, `y` is its baseline like with `draw_text`
+fn draw_centered_title(text: &str, y: f32, color: Color) {
+    let scale = TITLE_SIZE * 0.6;
+    let x = (SIZE.x - vector_text_width(text, scale) + scale) * 0.5;
+    draw_vector_text(text, Vec2::new(x, y - scale * 0.5), scale, color);
+}
+
+fn draw_centered_text(text: &str, size: f32, y: f32, color: Color) {
+    let dimensions = measure_text(text, None, size as u16, 1.0);
+    draw_text(text, (SIZE.x - dimensions.width) * 0.5, y, size, color);
+}
+
+fn draw_centered_number(number: usize, y: f32, color: Color) {
+    // draw_number grows to the left from the last digit
+    let digits = number.to_string().len() as f32;
+    draw_number(
+        number,
+        Vec2::new((SIZE.x + (digits - 1.0) * SCALE) * 0.5, y),
+        color,
+    );
+}
+
+/// The ship of the recorded run as far into it as the current run is
+fn render_ghost(state: &State, color: Color) {
+    let Some(ghost) = state.ghost.as_ref().filter(|_| state.show_ghost) else {
+        return;
+    };
+    if state.game_state == GameState::Menu || state.attract {
+        return;
+    }
+    if let Some(sample) = ghost
+        .at(state.now - state.run_start)
+        .filter(|sample| sample.alive)
+    {
+        let hull = transform_points(sample.position, SCALE, sample.rotation, &SHIP_POINTS);
+        draw_outline(&hull, true, with_alpha(color, 0.3));
+    }
+}
+
+/// Shades the cells of the rock collision grid, the more rocks in a cell the stronger
+fn render_rock_heatmap(state: &State) {
+    // a cell this full is drawn at full strength
+    const CROWDED: f32 = 6.0;
+
+    let grid = SpatialGrid::from_positions(
+        collision_cell_size(),
+        state.rocks.iter().map(|rock| rock.position),
+    );
+    for (cell, count) in grid.occupancy() {
+        let heat = (count as f32 / CROWDED).min(1.0);
+        let color = Color::new(1.0, 1.0 - heat, 0.0, 0.1 + heat * 0.3);
+        draw_rectangle(cell.x, cell.y, cell.w, cell.h, color);
+    }
+}
+
+/// Where everything is headed, each line covers the distance of the next few frames
+fn render_velocities(state: &State) {
+    const VELOCITY: Color = Color::new(0.0, 1.0, 0.5, 0.8);
+    const FRAMES: f32 = 8.0;
+
+    let vector = |position: Vec2, velocity: Vec2| {
+        // standing still has no direction to show
+        if velocity.length_squared() > f32::EPSILON {
+            draw_line_vec2(position, position + velocity * FRAMES, 1.0, VELOCITY);
+        }
+    };
+    for rock in state.rocks.iter() {
+        vector(rock.position, rock.velocity);
+    }
+    for alien in state.aliens.iter() {
+        vector(alien.position, alien.direction * alien.size.speed());
+    }
+    for projectile in state.projectiles.iter() {
+        vector(projectile.position, projectile.velocity);
+    }
+    for player in state.players.iter() {
+        if (&player.ship.status).into() {
+            vector(player.ship.position, player.ship.velocity);
+        }
+    }
+}
+
+/// What `update` actually collides: circles for everything but the ships, which collide
+/// with their outline. Drawn from the same `radius` methods, so they can't drift apart.
+fn render_hitboxes(state: &State) {
+    const HITBOX: Color = Color::new(1.0, 0.0, 1.0, 0.8);
+
+    let circle = |position: Vec2, radius: f32| {
+        draw_circle_lines(position.x, position.y, radius, 1.0, HITBOX);
+    };
+    for rock in state.rocks.iter() {
+        circle(rock.position, rock.radius());
+    }
+    for alien in state.aliens.iter() {
+        circle(alien.position, alien.radius());
+    }
+    for projectile in state.projectiles.iter() {
+        circle(projectile.position, projectile.radius());
+    }
+    for power_up in state.power_ups.iter() {
+        circle(power_up.position, power_up.collision_size());
+    }
+    if let Some(well) = &state.gravity_well {
+        circle(well.position, GravityWell::CORE_RADIUS);
+    }
+    for player in state.players.iter() {
+        if (&player.ship.status).into() {
+            let hull = player.ship.hitbox(state.config.ship_hitbox_scale);
+            for (i, &start) in hull.iter().enumerate() {
+                draw_line_vec2(start, hull[(i + 1) % hull.len()], 1.0, HITBOX);
+            }
+        }
+    }
+}
+
+fn render_debug_overlay(state: &State, outlines_time: Duration) {
+    const FONT_SIZE: f32 = 20.0;
+    let lines = [
+        format!("fps: {}", get_fps()),
+        format!(
+            "outlines: {:.2} ms, glow {}",
+            outlines_time.as_secs_f64() * 1000.0,
+            if state.config.phosphor_glow {
+                "on"
+            } else {
+                "off"
+            }
+        ),
+        format!("rocks: {}", state.rocks.len()),
+        format!(
+            "particles: {} / {}",
+            state.particles.len(),
+            state.particles.capacity()
+        ),
+        format!("projectiles: {}", state.projectiles.len()),
+        format!("aliens: {}", state.aliens.len()),
+        format!("now: {:.2}", state.now),
+        format!(
+            "hitboxes (F4): {}",
+            if state.debug_hitboxes { "on" } else { "off" }
+        ),
+        format!(
+            "rock heatmap (F6): {}",
+            if state.debug_heatmap { "on" } else { "off" }
+        ),
+        format!(
+            "frame step (F7): {}",
+            if state.frame_step {
+                "on, . steps"
+            } else {
+                "off"
+            }
+        ),
+        format!(
+            "velocities (F8): {}",
+            if state.debug_velocities { "on" } else { "off" }
+        ),
+    ];
+
+    // Bottom left corner, away from the lives (top left) and the score (top right)
+    let mut y = SIZE.y - SCALE * 0.5 - FONT_SIZE * (lines.len() - 1) as f32;
+    for line in lines.iter() {
+        draw_text(line, SCALE * 0.5, y, FONT_SIZE, state.theme.colors().line);
+        y += FONT_SIZE;
+    }
+}
+
+fn draw_number(number: usize, position: Vec2, color: Color) {
+    // grows to the left from the last digit
+    let digits = number.to_string();
+    let first = Vec2::new(position.x - (digits.len() - 1) as f32 * SCALE, position.y);
+    draw_vector_text(&digits, first, SCALE * 0.8, color);
+}
+
+/// Writes `text` in the line-art font, `position` is the center of the first character.
+/// Characters are `scale` tall with a quarter of that between them.
+fn draw_vector_text(text: &str, position: Vec2, scale: f32, color: Color) {
+    let advance = scale * 1.25;
+    for (index, c) in text.chars().enumerate() {
+        let Some(glyph) = glyph(c) else {
+            continue;
+        };
+        let center = position + Vec2::new(index as f32 * advance, 0.0);
+        for line in glyph.iter() {
+            draw_lines(center, scale, 0.0, line, false, color);
+        }
+    }
+}
+
+fn vector_text_width(text: &str, scale: f32) -> f32 {
+    let count = text.chars().count() as f32;
+    (count * 1.25 - 0.25).max(0.0) * scale
+}
+
+/// Draws `seconds` as M:SS centered on `position`
+fn draw_time(seconds: f32, position: Vec2, color: Color) {
+    const COLON: [Vec2; 2] = [Vec2::new(0.0, -0.2), Vec2::new(0.0, 0.2)];
+
+    let total = seconds.max(0.0) as usize;
+    let (minutes, seconds) = (total / 60, total % 60);
+    // the colon sits in the middle, the minutes grow to the left of it
+    let minutes_x = position.x - SCALE * 0.75;
+    draw_number(minutes, Vec2::new(minutes_x, position.y), color);
+    for dot in COLON.iter() {
+        draw_circle_vec2(*dot * SCALE + position, THICKNESS, color);
+    }
+    draw_number(
+        seconds / 10,
+        Vec2::new(position.x + SCALE * 0.75, position.y),
+        color,
+    );
+    draw_number(
+        seconds % 10,
+        Vec2::new(position.x + SCALE * 1.75, position.y),
+        color,
+    );
+}
+
+fn draw_multiplier(multiplier: usize, position: Vec2, color: Color) {
+    const CROSS: [[Vec2; 2]; 2] = [
+        [Vec2::new(-0.5, -0.5), Vec2::new(0.5, 0.5)],
+        [Vec2::new(-0.5, 0.5), Vec2::new(0.5, -0.5)],
+    ];
+
+    draw_number(multiplier, position, color);
+    let digits = multiplier.to_string().len() as f32;
+    let cross_position = Vec2::new(position.x - digits * SCALE, position.y);
+    for line in CROSS.iter() {
+        draw_lines(cross_position, SCALE * 0.5, 0.0, line, false, color);
+    }
+}
+
+fn draw_space_rock(rock: &Rock, scale: f32, color: Color, glow: bool) {
+    let points = rock_outline(&rock.size, rock.seed);
+    let size = rock.size.get_size() * scale;
+    // fades in as it grows at the start of a wave
+    let color = with_alpha(color, color.a * scale);
+    let (position, rotation) = (rock.position, rock.rotation);
+    draw_glowing_lines(position, size, rotation, &points, true, color, glow);
+    if rock.is_armored() {
+        draw_glowing_lines(position, size * 0.8, rotation, &points, true, color, glow);
+    }
+}
+
+/// A pulsing core with dots spiraling into it
+fn draw_gravity_well(pos: Vec2, now: f32, color: Color) {
+    const DOTS: usize = 24;
+
+    let pulse = 1.0 + 0.15 * (now * 4.0).sin();
+    draw_circle_lines(
+        pos.x,
+        pos.y,
+        GravityWell::CORE_RADIUS * pulse,
+        THICKNESS,
+        color,
+    );
+    for dot in 0..DOTS {
+        let progress = dot as f32 / DOTS as f32;
+        // each dot drifts inwards and starts over at the rim
+        let inwards = (progress + now * 0.3).fract();
+        let radius = GravityWell::CORE_RADIUS * (1.0 + 4.0 * (1.0 - inwards));
+        let angle = progress * std::f32::consts::TAU * 2.0 + inwards * std::f32::consts::PI;
+        draw_circle_vec2(
+            pos + Vec2::from_angle(angle) * radius,
+            1.5,
+            with_alpha(color, inwards),
+        );
+    }
+}
+
+fn draw_alien(pos: Vec2, size: &AlienSize, color: Color, glow: bool) {
+    let scale = match size {
+        AlienSize::Big => 1.0,
+        AlienSize::Small => 0.5,
+    };
+    let scale = SCALE * scale;
+
+    const MAIN: [Vec2; 8] = [
+        Vec2::new(-0.5, 0.0),
+        Vec2::new(-0.3, 0.3),
+        Vec2::splat(0.3),
+        Vec2::new(0.5, 0.0),
+        Vec2::new(0.3, -0.3),
+        Vec2::splat(-0.3),
+        Vec2::new(-0.5, 0.0),
+        Vec2::new(0.5, 0.0),
+    ];
+
+    draw_glowing_lines(pos, scale, 0.0, &MAIN, false, color, glow);
+
+    const CANOPY: [Vec2; 4] = [
+        Vec2::new(-0.2, -0.3),
+        Vec2::new(-0.1, -0.5),
+        Vec2::new(0.1, -0.5),
+        Vec2::new(0.2, -0.3),
+    ];
+
+    draw_glowing_lines(pos, scale, 0.0, &CANOPY, false, color, glow);
+}
+
+fn draw_power_up(pos: Vec2, kind: PowerUpKind, color: Color, glow: bool) {
+    const FRAME: [Vec2; 4] = [
+        Vec2::new(0.0, -0.5),
+        Vec2::new(0.5, 0.0),
+        Vec2::new(0.0, 0.5),
+        Vec2::new(-0.5, 0.0),
+    ];
+
+    // ore is a small crystal of its own, not a power-up in a frame
+    if kind == PowerUpKind::Ore {
+        const CRYSTAL: [Vec2; 6] = [
+            Vec2::new(0.0, -0.25),
+            Vec2::new(0.15, -0.1),
+            Vec2::new(0.12, 0.15),
+            Vec2::new(0.0, 0.25),
+            Vec2::new(-0.12, 0.15),
+            Vec2::new(-0.15, -0.1),
+        ];
+        draw_glowing_lines(pos, SCALE, 0.0, &CRYSTAL, true, color, glow);
+        draw_glowing_lines(pos, SCALE, 0.0, &CRYSTAL[..4], false, color, glow);
+        return;
+    }
+
+    draw_glowing_lines(pos, SCALE, 0.0, &FRAME, true, color, glow);
+
+    match kind {
+        PowerUpKind::Ore => {}
+        PowerUpKind::BulletTime => {
+            const HOURGLASS: [Vec2; 4] = [
+                Vec2::new(-0.15, -0.2),
+                Vec2::new(0.15, -0.2),
+                Vec2::new(-0.15, 0.2),
+                Vec2::new(0.15, 0.2),
+            ];
+            draw_glowing_lines(pos, SCALE, 0.0, &HOURGLASS, true, color, glow);
+        }
+        PowerUpKind::SpreadShot => {
+            const FAN: [[Vec2; 2]; 3] = [
+                [Vec2::new(0.0, 0.2), Vec2::new(0.0, -0.25)],
+                [Vec2::new(0.0, 0.2), Vec2::new(-0.2, -0.2)],
+                [Vec2::new(0.0, 0.2), Vec2::new(0.2, -0.2)],
+            ];
+            for line in FAN.iter() {
+                draw_glowing_lines(pos, SCALE, 0.0, line, false, color, glow);
+            }
+        }
+        PowerUpKind::Shield => {
+            draw_circle_lines(pos.x, pos.y, SCALE * 0.2, THICKNESS, color);
+        }
+        PowerUpKind::Bomb => {
+            draw_circle_lines(pos.x, pos.y + SCALE * 0.05, SCALE * 0.15, THICKNESS, color);
+            // the fuse
+            draw_glowing_lines(
+                pos,
+                SCALE,
+                0.0,
+                &[Vec2::new(0.08, -0.08), Vec2::new(0.18, -0.2)],
+                false,
+                color,
+                glow,
+            );
+        }
+        PowerUpKind::RapidFire => {
+            // a stream of shots
+            for offset in [-0.15, 0.0, 0.15] {
+                draw_glowing_lines(
+                    pos,
+                    SCALE,
+                    0.0,
+                    &[Vec2::new(-0.2, offset), Vec2::new(0.2, offset)],
+                    false,
+                    color,
+                    glow,
+                );
+            }
+        }
+        PowerUpKind::Ricochet => {
+            const ZIGZAG: [Vec2; 4] = [
+                Vec2::new(-0.2, -0.2),
+                Vec2::new(-0.05, 0.2),
+                Vec2::new(0.05, -0.2),
+                Vec2::new(0.2, 0.2),
+            ];
+            draw_glowing_lines(pos, SCALE, 0.0, &ZIGZAG, false, color, glow);
+        }
+        PowerUpKind::Magnet => {
+            // a horseshoe with its poles at the top
+            const HORSESHOE: [Vec2; 6] = [
+                Vec2::new(-0.18, -0.2),
+                Vec2::new(-0.18, 0.05),
+                Vec2::new(-0.08, 0.18),
+                Vec2::new(0.08, 0.18),
+                Vec2::new(0.18, 0.05),
+                Vec2::new(0.18, -0.2),
+            ];
+            draw_glowing_lines(pos, SCALE, 0.0, &HORSESHOE, false, color, glow);
+            for x in [-0.18, 0.18] {
+                draw_glowing_lines(
+                    pos,
+                    SCALE,
+                    0.0,
+                    &[Vec2::new(x - 0.06, -0.1), Vec2::new(x + 0.06, -0.1)],
+                    false,
+                    color,
+                    glow,
+                );
+            }
+        }
+    }
+}
+
+/// Rotates, scales and moves model points into playfield coordinates
+pub(crate) fn transform_points(
+    origin: Vec2,
+    scale: f32,
+    rotation: f32,
+    points: &[Vec2],
+) -> Vec<Vec2> {
+    let rotation_vec = Vec2::from_angle(rotation);
+    points
+        .iter()
+        .map(|p| (p.rotate(rotation_vec) * scale) + origin)
+        .collect()
+}
+
+fn draw_lines(
+    origin: Vec2,
+    scale: f32,
+    rotation: f32,
+    points: &[Vec2],
+    connect: bool,
+    color: Color,
+) {
+    draw_outline(
+        &transform_points(origin, scale, rotation, points),
+        connect,
+        color,
+    );
+}
+
+/// Like `draw_lines`, with the phosphor bloom around the lines if `glow` is set
+fn draw_glowing_lines(
+    origin: Vec2,
+    scale: f32,
+    rotation: f32,
+    points: &[Vec2],
+    connect: bool,
+    color: Color,
+    glow: bool,
+) {
+    draw_glowing_outline(
+        &transform_points(origin, scale, rotation, points),
+        connect,
+        color,
+        glow,
+    );
+}
+
+/// A vector monitor's phosphor bloom: wide faint strokes underneath, narrower and
+/// brighter ones towards the middle and a core a little whiter than `color` on top.
+/// Each line is drawn three times, so it is left to the playfield's outlines.
+fn draw_glowing_outline(points: &[Vec2], connect: bool, color: Color, glow: bool) {
+    // thickness and alpha of the strokes beneath the core, widest first
+    const BLOOM: [(f32, f32); 2] = [(THICKNESS * 4.0, 0.08), (THICKNESS * 2.2, 0.2)];
+
+    if !glow {
+        draw_outline(points, connect, color);
+        return;
+    }
+    let length = if connect {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    for (thickness, alpha) in BLOOM {
+        let stroke = with_alpha(color, color.a * alpha);
+        for i in 0..length {
+            draw_line_vec2(points[i], points[(i + 1) % points.len()], thickness, stroke);
+        }
+    }
+    let core = Color::new(
+        color.r + (1.0 - color.r) * 0.3,
+        color.g + (1.0 - color.g) * 0.3,
+        color.b + (1.0 - color.b) * 0.3,
+        color.a,
+    );
+    draw_outline(points, connect, core);
+}
+
+/// Draws points that are already in playfield coordinates
+fn draw_outline(points: &[Vec2], connect: bool, color: Color) {
+    let length = if connect {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    for i in 0..length {
+        let wrap = (i + 1) % points.len();
+        //debug!("i {}, wrap: {}", i, wrap);
+        let pos1 = points.get(i).unwrap();
+        let pos2 = points.get(wrap).unwrap();
+        draw_line_vec2(*pos1, *pos2, THICKNESS, color);
+    }
+}
+
+fn draw_circle_vec2(pos: Vec2, radius: f32, color: Color) {
+    draw_circle(pos.x, pos.y, radius, color);
+}
+
+fn draw_line_vec2(pos1: Vec2, pos2: Vec2, thickness: f32, color: Color) {
+    draw_line(pos1.x, pos1.y, pos2.x, pos2.y, thickness, color);
+}
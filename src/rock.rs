@@ -0,0 +1,474 @@
+use ::rand::Rng;
+use macroquad::prelude::*;
+use rand_xoshiro::{rand_core::SeedableRng, Xoshiro256StarStar};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::WrapMode, game::playfield_delta, grid::SpatialGrid, SCALE, SIZE};
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Rock {
+    pub(crate) position: Vec2,
+    pub(crate) velocity: Vec2,
+    pub(crate) size: RockSize,
+    pub(crate) seed: u64,
+    pub(crate) removed: bool,
+    pub(crate) health: u32,
+    // radians, only applied when drawing so the outline keeps coming from `seed`
+    pub(crate) rotation: f32,
+    pub(crate) angular_velocity: f32,
+    // fragments can't hit a ship until then, see `Rock::FRAGMENT_GRACE`
+    pub(crate) harmless_until: f32,
+    // when a rock of a new wave appeared, it grows in during `Rock::INTRO`.
+    // Fragments and older saves start out fully grown.
+    #[serde(default = "grown")]
+    pub(crate) spawned: f32,
+}
+
+fn grown() -> f32 {
+    -Rock::INTRO
+}
+
+impl Default for Rock {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            size: RockSize::Big,
+            seed: 0,
+            removed: false,
+            health: 1,
+            rotation: 0.0,
+            angular_velocity: 0.0,
+            harmless_until: 0.0,
+            spawned: grown(),
+        }
+    }
+}
+
+impl Rock {
+    // seconds a fresh fragment passes through ships, it may have split right on top of one
+    pub(crate) const FRAGMENT_GRACE: f32 = 0.25;
+    // seconds the rocks of a new wave take to grow in, passing through ships meanwhile
+    pub(crate) const INTRO: f32 = 0.5;
+
+    pub(crate) fn is_armored(&self) -> bool {
+        self.health > 1
+    }
+
+    /// Whether it can destroy a ship at `now`
+    pub(crate) fn can_hit_ship(&self, now: f32) -> bool {
+        now >= self.harmless_until
+    }
+
+    /// How far it has grown in at `now`, from 0 right at the start of a wave to 1
+    pub(crate) fn intro_scale(&self, now: f32) -> f32 {
+        ((now - self.spawned) / Rock::INTRO).clamp(0.0, 1.0)
+    }
+
+    /// Radius of the circle it collides with, a bit inside the drawn outline
+    pub(crate) fn radius(&self) -> f32 {
+        self.size.get_size() * self.size.get_collision_scale()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum RockSize {
+    // never rolled randomly, only spawned at score thresholds
+    Boss,
+    Big,
+    Medium,
+    Small,
+}
+
+impl RockSize {
+    pub fn get_size(self: &Self) -> f32 {
+        match self {
+            RockSize::Boss => SCALE * 5.0,
+            RockSize::Big => SCALE * 3.0,
+            RockSize::Medium => SCALE * 1.4,
+            RockSize::Small => SCALE * 0.8,
+        }
+    }
+
+    pub fn get_score(self: &Self) -> usize {
+        match self {
+            RockSize::Boss => 1000,
+            RockSize::Big => 20,
+            RockSize::Medium => 50,
+            RockSize::Small => 100,
+        }
+    }
+
+    pub fn get_collision_scale(self: &Self) -> f32 {
+        match self {
+            RockSize::Boss => 0.4,
+            RockSize::Big => 0.4,
+            RockSize::Medium => 0.65,
+            RockSize::Small => 1.0,
+        }
+    }
+
+    pub fn get_shake(&self) -> f32 {
+        match self {
+            RockSize::Boss => 14.0,
+            RockSize::Big => 6.0,
+            RockSize::Medium => 3.0,
+            RockSize::Small => 1.5,
+        }
+    }
+
+    pub fn get_shockwave(&self) -> f32 {
+        match self {
+            RockSize::Boss => 4.0,
+            RockSize::Big => 2.0,
+            RockSize::Medium => 1.2,
+            RockSize::Small => 0.6,
+        }
+    }
+
+    pub fn get_velocity(self: &Self) -> f32 {
+        match self {
+            RockSize::Boss => 0.3,
+            RockSize::Big => 0.75,
+            RockSize::Medium => 1.0,
+            RockSize::Small => 1.6,
+        }
+    }
+
+    /// Fastest spin in radians per frame, heavy rocks turn slowly
+    pub fn get_spin(&self) -> f32 {
+        match self {
+            RockSize::Boss => 0.002,
+            RockSize::Big => 0.005,
+            RockSize::Medium => 0.01,
+            RockSize::Small => 0.02,
+        }
+    }
+
+    pub fn new(size: f32) -> Self {
+        if size < 0.3 {
+            RockSize::Small
+        } else if size >= 0.3 && size < 0.59 {
+            RockSize::Medium
+        } else {
+            RockSize::Big
+        }
+    }
+}
+
+impl From<f32> for RockSize {
+    fn from(value: f32) -> Self {
+        RockSize::new(value)
+    }
+}
+
+/// How the rocks of a wave are laid out, the first wave is always scattered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SpawnPattern {
+    // anywhere, drifting every which way
+    Scatter,
+    // a dense band across the playfield, every rock drifting along it
+    Belt { angle: f32 },
+    // around the middle, every rock drifting outwards
+    Ring,
+    // a few dense clumps, drifting every which way
+    Clumps { centers: [Vec2; 3] },
+}
+
+impl SpawnPattern {
+    /// Cycles through the patterns wave by wave. Only what a pattern is laid out by
+    /// comes from `random`, so a seeded run gets the same waves every time.
+    pub(crate) fn for_wave(wave: usize, random: &mut impl Rng) -> Self {
+        match wave.saturating_sub(1) % 4 {
+            0 => SpawnPattern::Scatter,
+            1 => SpawnPattern::Belt {
+                angle: std::f32::consts::TAU * random.gen::<f32>(),
+            },
+            2 => SpawnPattern::Ring,
+            _ => SpawnPattern::Clumps {
+                centers: [(); 3].map(|_| SIZE * Vec2::new(random.gen(), random.gen())),
+            },
+        }
+    }
+
+    /// A random spot following the pattern, at least `margin` away from the edges
+    pub(crate) fn position(&self, random: &mut impl Rng, margin: f32) -> Vec2 {
+        let spot = match self {
+            SpawnPattern::Scatter => {
+                return Vec2::new(
+                    margin + random.gen::<f32>() * (SIZE.x - margin * 2.0),
+                    margin + random.gen::<f32>() * (SIZE.y - margin * 2.0),
+                );
+            }
+            SpawnPattern::Belt { angle } => {
+                // long enough to cross the playfield, wrapping back onto it
+                let along = (random.gen::<f32>() - 0.5) * SIZE.length();
+                let across = (random.gen::<f32>() - 0.5) * SCALE * 4.0;
+                let direction = Vec2::from_angle(*angle);
+                let spot = SIZE * 0.5 + direction * along + direction.perp() * across;
+                Vec2::new(spot.x.rem_euclid(SIZE.x), spot.y.rem_euclid(SIZE.y))
+            }
+            SpawnPattern::Ring => {
+                // follows the shape of the playfield, with room left for the ship inside
+                let angle = std::f32::consts::TAU * random.gen::<f32>();
+                let radius = 0.42 + (random.gen::<f32>() - 0.5) * 0.06;
+                SIZE * 0.5 + Vec2::from_angle(angle) * SIZE * radius
+            }
+            SpawnPattern::Clumps { centers } => {
+                let center = centers[random.gen_range(0..centers.len())];
+                let angle = std::f32::consts::TAU * random.gen::<f32>();
+                center + Vec2::from_angle(angle) * random.gen::<f32>() * SCALE * 3.0
+            }
+        };
+        spot.clamp(Vec2::splat(margin), SIZE - margin)
+    }
+
+    /// Which way a rock spawned at `position` drifts, `angle` is its own random one
+    pub(crate) fn direction(&self, position: Vec2, angle: f32) -> Vec2 {
+        match self {
+            SpawnPattern::Scatter | SpawnPattern::Clumps { .. } => Vec2::from_angle(angle),
+            // a little spread, still clearly one stream
+            SpawnPattern::Belt { angle: belt } => Vec2::from_angle(belt + (angle.sin() * 0.15)),
+            SpawnPattern::Ring => (position - SIZE * 0.5)
+                .try_normalize()
+                .unwrap_or_else(|| Vec2::from_angle(angle)),
+        }
+    }
+}
+
+/// The overall look of a rock's outline, picked from its seed so it stays the same
+#[derive(Clone, Copy)]
+enum RockStyle {
+    Plain,
+    // round with hardly any dents
+    Chunky,
+    // deep notches between long spikes
+    Jagged,
+    // drawn out to one side
+    Elongated,
+}
+
+impl RockStyle {
+    fn pick(random: &mut Xoshiro256StarStar) -> Self {
+        match random.gen_range(0..4) {
+            0 => RockStyle::Chunky,
+            1 => RockStyle::Jagged,
+            2 => RockStyle::Elongated,
+            _ => RockStyle::Plain,
+        }
+    }
+
+    fn base_radius(&self) -> f32 {
+        match self {
+            RockStyle::Chunky => 0.4,
+            RockStyle::Jagged => 0.25,
+            _ => 0.3,
+        }
+    }
+
+    fn variance(&self) -> f32 {
+        match self {
+            RockStyle::Chunky => 0.08,
+            RockStyle::Jagged => 0.3,
+            _ => 0.2,
+        }
+    }
+
+    fn dent_chance(&self) -> f32 {
+        match self {
+            RockStyle::Chunky => 0.05,
+            RockStyle::Jagged => 0.35,
+            _ => 0.2,
+        }
+    }
+
+    fn stretch(&self) -> f32 {
+        match self {
+            RockStyle::Elongated => 1.35,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Model points of the outline of a rock, the same for the same `seed` every time.
+/// Nothing but the seed goes into it, so a rock looks the same after loading or in a replay.
+pub(crate) fn rock_outline(size: &RockSize, seed: u64) -> Vec<Vec2> {
+    let mut random = Xoshiro256StarStar::seed_from_u64(seed);
+    let mut points: Vec<Vec2> = Vec::with_capacity(24);
+    let boss = *size == RockSize::Boss;
+    let style = RockStyle::pick(&mut random);
+    let n = if boss {
+        random.gen_range(18..24)
+    } else {
+        random.gen_range(8..15)
+    };
+    for i in 0..n {
+        let mut radius = style.base_radius() + (style.variance() * random.gen::<f32>());
+        if boss {
+            // alternating spikes and notches give the boss its jagged outline
+            radius = if i % 2 == 0 { 0.5 } else { 0.32 } + 0.05 * random.gen::<f32>();
+        } else if random.gen::<f32>() < style.dent_chance() {
+            radius -= 0.2;
+        }
+        let angle = i as f32 * (std::f32::consts::TAU / n as f32)
+            + (std::f32::consts::PI * 0.125 * random.gen::<f32>());
+        let direction = Vec2::from_angle(angle);
+        points.push(direction * radius);
+    }
+    if !boss {
+        // stretched along the model's x axis, the rotation spreads it around
+        let stretch = style.stretch();
+        for point in points.iter_mut() {
+            *point *= Vec2::new(stretch, 1.0 / stretch);
+        }
+    }
+    points
+}
+
+/// Cell size for a `SpatialGrid` of things colliding with rocks. A cell is as wide as
+/// the biggest rock, so anything touching a rock is at most one cell away from it.
+pub(crate) fn collision_cell_size() -> f32 {
+    RockSize::Boss.get_size() * RockSize::Boss.get_collision_scale() * 2.0
+}
+
+/// Bounces overlapping rocks off each other. Rocks are bucketed into a grid first
+/// so only rocks in neighbouring cells have to be compared.
+pub(crate) fn collide_rocks(rocks: &mut [Rock], wrap_mode: WrapMode) {
+    let grid = SpatialGrid::from_positions(
+        collision_cell_size(),
+        rocks.iter().map(|rock| rock.position),
+    );
+    for first in 0..rocks.len() {
+        for second in grid.near(rocks[first].position) {
+            if second > first {
+                bounce_rocks(rocks, first, second, wrap_mode);
+            }
+        }
+    }
+}
+
+fn bounce_rocks(rocks: &mut [Rock], first: usize, second: usize, wrap_mode: WrapMode) {
+    let (head, tail) = rocks.split_at_mut(second);
+    let (first, second) = (&mut head[first], &mut tail[0]);
+    if first.removed || second.removed {
+        return;
+    }
+
+    let delta = playfield_delta(wrap_mode, first.position, second.position);
+    if delta.length() >= first.radius() + second.radius() {
+        return;
+    }
+    let Some(normal) = delta.try_normalize() else {
+        return;
+    };
+
+    // Rocks already moving apart are left alone, so rocks spawned on top of
+    // each other drift apart instead of being pushed out violently
+    let approach = (first.velocity - second.velocity).dot(normal);
+    if approach <= 0.0 {
+        return;
+    }
+
+    // elastic collision along the normal, heavier rocks get pushed less
+    let first_mass = first.size.get_size().powi(2);
+    let second_mass = second.size.get_size().powi(2);
+    let impulse = 2.0 * approach / (first_mass + second_mass);
+    first.velocity -= normal * impulse * second_mass;
+    second.velocity += normal * impulse * first_mass;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn belts_drift_together_and_rings_outwards() {
+        let mut random = Xoshiro256StarStar::seed_from_u64(883);
+        let margin = SCALE;
+        let belt = SpawnPattern::for_wave(2, &mut random);
+        let SpawnPattern::Belt { angle } = belt else {
+            panic!("the second wave is a belt, got {:?}", belt);
+        };
+        for _ in 0..50 {
+            let position = belt.position(&mut random, margin);
+            let inner = SIZE - margin;
+            assert!(position.x >= margin && position.y >= margin);
+            assert!(position.x <= inner.x && position.y <= inner.y);
+            let direction = belt.direction(position, random.gen::<f32>() * 6.0);
+            assert!(direction.dot(Vec2::from_angle(angle)) > 0.9);
+        }
+
+        let ring = SpawnPattern::for_wave(3, &mut random);
+        for _ in 0..50 {
+            let position = ring.position(&mut random, margin);
+            let outwards = (position - SIZE * 0.5).normalize();
+            assert!(ring.direction(position, 0.0).dot(outwards) > 0.99);
+        }
+    }
+
+    #[test]
+    fn outlines_only_depend_on_the_seed() {
+        assert_eq!(
+            rock_outline(&RockSize::Big, 866),
+            rock_outline(&RockSize::Big, 866)
+        );
+        assert_ne!(
+            rock_outline(&RockSize::Big, 866),
+            rock_outline(&RockSize::Big, 867)
+        );
+    }
+
+    #[test]
+    fn classifies_sizes_at_boundaries() {
+        assert_eq!(RockSize::new(0.0), RockSize::Small);
+        assert_eq!(RockSize::new(0.29), RockSize::Small);
+        assert_eq!(RockSize::new(0.3), RockSize::Medium);
+        assert_eq!(RockSize::new(0.58), RockSize::Medium);
+        assert_eq!(RockSize::new(0.59), RockSize::Big);
+        assert_eq!(RockSize::new(1.0), RockSize::Big);
+        assert_eq!(RockSize::from(0.3), RockSize::Medium);
+    }
+
+    #[test]
+    fn scores_grow_as_rocks_shrink() {
+        assert_eq!(RockSize::Big.get_score(), 20);
+        assert_eq!(RockSize::Medium.get_score(), 50);
+        assert_eq!(RockSize::Small.get_score(), 100);
+    }
+
+    #[test]
+    fn size_table() {
+        assert_eq!(RockSize::Boss.get_size(), SCALE * 5.0);
+        assert_eq!(RockSize::Big.get_size(), SCALE * 3.0);
+        assert_eq!(RockSize::Medium.get_size(), SCALE * 1.4);
+        assert_eq!(RockSize::Small.get_size(), SCALE * 0.8);
+    }
+
+    #[test]
+    fn velocity_table() {
+        assert_eq!(RockSize::Boss.get_velocity(), 0.3);
+        assert_eq!(RockSize::Big.get_velocity(), 0.75);
+        assert_eq!(RockSize::Medium.get_velocity(), 1.0);
+        assert_eq!(RockSize::Small.get_velocity(), 1.6);
+    }
+
+    #[test]
+    fn collision_scale_table() {
+        assert_eq!(RockSize::Big.get_collision_scale(), 0.4);
+        assert_eq!(RockSize::Medium.get_collision_scale(), 0.65);
+        assert_eq!(RockSize::Small.get_collision_scale(), 1.0);
+    }
+
+    #[test]
+    fn bigger_rocks_spin_slower() {
+        let sizes = [
+            RockSize::Boss,
+            RockSize::Big,
+            RockSize::Medium,
+            RockSize::Small,
+        ];
+        for pair in sizes.windows(2) {
+            assert!(pair[0].get_spin() < pair[1].get_spin());
+        }
+    }
+}
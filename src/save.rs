@@ -0,0 +1,95 @@
+use std::{fs, io, mem};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::game::State;
+
+/// Where F5 saves the run and F9 picks it up again
+pub(crate) const SAVE_PATH: &str = "./savegame.json";
+
+pub(crate) fn save(state: &State, path: &str) -> io::Result<()> {
+    let content = serde_json::to_string(state)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    fs::write(path, content)
+}
+
+/// Replaces the run in `state` with the one saved at `path`
+pub(crate) fn load(state: &mut State, path: &str) -> io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let loaded = serde_json::from_str(&content)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    restore(state, loaded)
+}
+
+/// Puts a deserialized run in place, keeping what belongs to the session rather than the
+/// run. The random number generator is part of the run, so with the same input from here
+/// on it plays out exactly like the run that was saved.
+pub(crate) fn restore(state: &mut State, mut loaded: State) -> io::Result<()> {
+    // the input is set up for the configured number of ships
+    if loaded.players.len() != state.player_count() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "saved with {} players, the config is set up for {}",
+                loaded.players.len(),
+                state.player_count()
+            ),
+        ));
+    }
+    // a daily challenge plays with its own config, which only the one being played has
+    if loaded.daily != state.daily {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "saved in a different daily challenge than the one being played",
+        ));
+    }
+    mem::swap(&mut loaded.audio, &mut state.audio);
+    mem::swap(&mut loaded.config, &mut state.config);
+    mem::swap(&mut loaded.daily_stash, &mut state.daily_stash);
+    loaded.recorded_input = state.recorded_input;
+    mem::swap(&mut loaded.ghost, &mut state.ghost);
+    mem::swap(&mut loaded.run_log, &mut state.run_log);
+    mem::swap(&mut loaded.music, &mut state.music);
+    mem::swap(&mut loaded.achievements, &mut state.achievements);
+    mem::swap(&mut loaded.high_scores, &mut state.high_scores);
+    loaded.particles.set_limit(loaded.config.max_particles);
+    loaded.show_ghost = state.show_ghost;
+    loaded.high_score = state.high_score;
+    loaded.endless_high_score = state.endless_high_score;
+    loaded.volume = state.volume;
+    loaded.music_volume = state.music_volume;
+    loaded.music_available = state.music_available;
+    loaded.muted = state.muted;
+    loaded.debug = state.debug;
+    loaded.alien_indicators = state.alien_indicators;
+    loaded.aliens_enabled = state.aliens_enabled;
+    loaded.theme = state.theme;
+    loaded.density = state.density;
+    loaded.flight_mode = state.flight_mode;
+    loaded.start_lifes = state.start_lifes;
+    loaded.fullscreen = state.fullscreen;
+    *state = loaded;
+    Ok(())
+}
+
+/// `Option<Color>` as an optional `[r, g, b, a]`, macroquad's colors know nothing of serde
+pub(crate) mod optional_color {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        color: &Option<Color>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        color
+            .map(|color| [color.r, color.g, color.b, color.a])
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Color>, D::Error> {
+        let color: Option<[f32; 4]> = Option::deserialize(deserializer)?;
+        Ok(color.map(|[r, g, b, a]| Color::new(r, g, b, a)))
+    }
+}
@@ -0,0 +1,248 @@
+use std::fs;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{game::LIFES, theme::ThemeKind};
+
+const SETTINGS_PATH: &str = "./settings.toml";
+
+/// How crowded the fields are generated, cycled with N
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Density {
+    Sparse,
+    #[default]
+    Normal,
+    Dense,
+}
+
+impl Density {
+    /// Scales the rock count of a field along with its armored rock and power-up chances
+    pub(crate) fn multiplier(&self) -> f32 {
+        match self {
+            Density::Sparse => 0.6,
+            Density::Normal => 1.0,
+            Density::Dense => 1.5,
+        }
+    }
+
+    pub(crate) fn next(&self) -> Self {
+        match self {
+            Density::Sparse => Density::Normal,
+            Density::Normal => Density::Dense,
+            Density::Dense => Density::Sparse,
+        }
+    }
+
+    pub(crate) fn previous(&self) -> Self {
+        match self {
+            Density::Sparse => Density::Dense,
+            Density::Normal => Density::Sparse,
+            Density::Dense => Density::Normal,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Density::Sparse => "SPARSE",
+            Density::Normal => "NORMAL",
+            Density::Dense => "DENSE",
+        }
+    }
+}
+
+/// How the ship moves once the thrust lets go
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FlightMode {
+    // drifts on for a long while, only the configured drag slows it
+    #[default]
+    Classic,
+    // comes to a stop soon after, with a short drift left
+    Precision,
+}
+
+impl FlightMode {
+    pub(crate) fn toggled(&self) -> Self {
+        match self {
+            FlightMode::Classic => FlightMode::Precision,
+            FlightMode::Precision => FlightMode::Classic,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            FlightMode::Classic => "CLASSIC",
+            FlightMode::Precision => "PRECISION",
+        }
+    }
+
+    /// Share of its velocity the ship loses in a frame, `drag` is the configured one
+    pub(crate) fn drag(&self, drag: f32, thrusting: bool) -> f32 {
+        // about a fifth of a second to lose half its speed
+        const PRECISION_DRAG: f32 = 0.06;
+
+        match self {
+            FlightMode::Precision if !thrusting => drag.max(PRECISION_DRAG),
+            _ => drag,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub(crate) volume: f32,
+    // of the music alone, on top of `volume`
+    pub(crate) music_volume: f32,
+    pub(crate) muted: bool,
+    pub(crate) alien_indicators: bool,
+    // off for rocks only, nothing but the rocks gets harder then
+    pub(crate) aliens: bool,
+    pub(crate) theme: ThemeKind,
+    pub(crate) density: Density,
+    pub(crate) flight_mode: FlightMode,
+    // the ships a run starts with
+    pub(crate) lives: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            music_volume: 0.6,
+            muted: false,
+            alien_indicators: true,
+            aliens: true,
+            theme: ThemeKind::Classic,
+            density: Density::Normal,
+            flight_mode: FlightMode::Classic,
+            lives: LIFES,
+        }
+    }
+}
+
+/// The settings that change how a run plays out, written into the header of a replay
+/// so it plays back the same whatever the player has set since
+#[derive(Clone, Copy)]
+pub(crate) struct RunSettings {
+    density: Density,
+    aliens: bool,
+    flight_mode: FlightMode,
+    lives: usize,
+}
+
+impl Default for RunSettings {
+    fn default() -> Self {
+        Self::of(&Settings::default())
+    }
+}
+
+impl RunSettings {
+    pub(crate) fn of(settings: &Settings) -> Self {
+        Self {
+            density: settings.density,
+            aliens: settings.aliens,
+            flight_mode: settings.flight_mode,
+            lives: settings.lives,
+        }
+    }
+
+    /// Overrides the ones of `settings`, everything else (volume, theme, ...) stays
+    pub(crate) fn apply(&self, settings: &mut Settings) {
+        settings.density = self.density;
+        settings.aliens = self.aliens;
+        settings.flight_mode = self.flight_mode;
+        settings.lives = self.lives;
+    }
+
+    pub(crate) fn to_header(self) -> String {
+        format!(
+            "settings {} {} {} {}",
+            self.density.name(),
+            self.aliens,
+            self.flight_mode.name(),
+            self.lives
+        )
+    }
+
+    /// None unless `line` was written by `to_header`
+    pub(crate) fn from_header(line: &str) -> Option<Self> {
+        let mut words = line.strip_prefix("settings ")?.split(' ');
+        let density = words.next()?;
+        let density = [Density::Sparse, Density::Normal, Density::Dense]
+            .into_iter()
+            .find(|candidate| candidate.name() == density)?;
+        let aliens = words.next()?.parse().ok()?;
+        let flight_mode = words.next()?;
+        let flight_mode = [FlightMode::Classic, FlightMode::Precision]
+            .into_iter()
+            .find(|candidate| candidate.name() == flight_mode)?;
+        // recorded before the lives could be picked
+        let lives = match words.next() {
+            Some(lives) => lives.parse().ok()?,
+            None => LIFES,
+        };
+        Some(Self {
+            density,
+            aliens,
+            flight_mode,
+            lives,
+        })
+    }
+}
+
+impl Settings {
+    pub(crate) fn load() -> Self {
+        fs::read_to_string(SETTINGS_PATH)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn save(&self) {
+        match toml::to_string(self) {
+            Ok(content) => {
+                if let Err(err) = fs::write(SETTINGS_PATH, content) {
+                    warn!("Unable to write settings: {}", err);
+                }
+            }
+            Err(err) => warn!("Unable to serialize settings: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_settings_survive_the_replay_header() {
+        let settings = Settings {
+            density: Density::Dense,
+            aliens: false,
+            flight_mode: FlightMode::Precision,
+            lives: 5,
+            ..Default::default()
+        };
+        let header = RunSettings::of(&settings).to_header();
+        assert_eq!(header, "settings DENSE false PRECISION 5");
+
+        let mut played = Settings::default();
+        RunSettings::from_header(&header)
+            .unwrap()
+            .apply(&mut played);
+        assert!(played.density == Density::Dense);
+        assert!(!played.aliens);
+        assert!(played.flight_mode == FlightMode::Precision);
+        assert_eq!(played.lives, 5);
+
+        let older = RunSettings::from_header("settings NORMAL true CLASSIC").unwrap();
+        older.apply(&mut played);
+        assert_eq!(played.lives, LIFES);
+
+        assert!(RunSettings::from_header("0.016 0 0 0").is_none());
+        assert!(RunSettings::from_header("settings HUGE true CLASSIC").is_none());
+    }
+}
@@ -0,0 +1,309 @@
+use std::ops::Mul;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::WrapMode,
+    game::playfield_delta,
+    projectile::{Projectile, ProjectileKind},
+    render::transform_points,
+    SCALE, SIZE,
+};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct DeathTime {
+    pub(crate) death_timer: f32,
+    pub(crate) death_time: f32,
+    // how whatever destroyed the ship moved relative to it, the explosion follows along
+    #[serde(default)]
+    pub(crate) impact: Option<Vec2>,
+}
+
+impl DeathTime {
+    /// Died at `time`, back after `respawn_delay` seconds unless skipped
+    pub(crate) fn new(time: f32, respawn_delay: f32) -> Self {
+        Self {
+            death_timer: time + respawn_delay,
+            death_time: time,
+            impact: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ShipStatus {
+    Alive,
+    Dead(DeathTime),
+}
+
+impl From<&ShipStatus> for bool {
+    fn from(value: &ShipStatus) -> Self {
+        match value {
+            ShipStatus::Alive => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Weapon {
+    Single,
+    Spread,
+    Ricochet,
+}
+
+impl Weapon {
+    /// Angles relative to the ship's heading, one projectile per entry
+    pub(crate) fn angles(&self) -> &'static [f32] {
+        const SPREAD: f32 = 15.0 * std::f32::consts::PI / 180.0;
+        match self {
+            Weapon::Single | Weapon::Ricochet => &[0.0],
+            Weapon::Spread => &[0.0, SPREAD, -SPREAD],
+        }
+    }
+
+    pub(crate) fn projectile_kind(&self) -> ProjectileKind {
+        match self {
+            Weapon::Single | Weapon::Spread => ProjectileKind::Straight,
+            Weapon::Ricochet => ProjectileKind::Ricochet {
+                bounces: Projectile::RICOCHET_BOUNCES,
+            },
+        }
+    }
+
+    /// Seconds a regular shot of this weapon lives, `base` being the configured shot lifetime
+    pub(crate) fn projectile_lifetime(&self, base: f32) -> f32 {
+        match self {
+            Weapon::Single | Weapon::Spread => base,
+            // long enough to make use of a bounce or two
+            Weapon::Ricochet => base * 1.6,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Ship {
+    pub(crate) position: Vec2,
+    pub(crate) velocity: Vec2,
+    pub(crate) rotation: f32,
+    // radians per second, keyboard and gamepad turns build up and wind down through it
+    pub(crate) angular_velocity: f32,
+    pub(crate) status: ShipStatus,
+    pub(crate) last_shot: f32,
+    // seconds the fire button has been held
+    pub(crate) charge: f32,
+    // absorbs the next lethal hit
+    pub(crate) shielded: bool,
+    // nothing can hit the ship before this time, e.g. right after the shield broke
+    pub(crate) invulnerable_until: f32,
+    pub(crate) last_dash: f32,
+}
+
+impl Ship {
+    // seconds fire has to be held for a charged shot
+    pub(crate) const FULL_CHARGE: f32 = 0.8;
+
+    /// Whether the ship is alive and can currently be hit
+    pub(crate) fn is_vulnerable(&self, now: f32) -> bool {
+        let alive: bool = (&self.status).into();
+        alive && now >= self.invulnerable_until
+    }
+
+    /// Brings the turn rate towards `target` radians per second, letting go of a turn
+    /// stops it quicker than it got going
+    pub(crate) fn steer(&mut self, target: f32, delta: f32) {
+        // radians per second squared
+        const TURN_ACCELERATION: f32 = 80.0;
+        const TURN_DECELERATION: f32 = 140.0;
+
+        let slowing = target.abs() < self.angular_velocity.abs()
+            || target.signum() != self.angular_velocity.signum();
+        let rate = if slowing {
+            TURN_DECELERATION
+        } else {
+            TURN_ACCELERATION
+        };
+        let step = rate * delta;
+        self.angular_velocity += (target - self.angular_velocity).clamp(-step, step);
+    }
+
+    /// Outline in playfield coordinates, exactly as it is drawn
+    pub(crate) fn hull(&self) -> Vec<Vec2> {
+        transform_points(self.position, SCALE, self.rotation, &SHIP_POINTS)
+    }
+
+    /// The outline scaled around the ship's center, what it actually collides with
+    pub(crate) fn hitbox(&self, scale: f32) -> Vec<Vec2> {
+        transform_points(self.position, SCALE * scale, self.rotation, &SHIP_POINTS)
+    }
+
+    /// Whether a circle touches the ship's `hitbox` rather than just its surroundings
+    pub(crate) fn overlaps_circle(
+        &self,
+        center: Vec2,
+        radius: f32,
+        hitbox_scale: f32,
+        wrap_mode: WrapMode,
+    ) -> bool {
+        // the copy of the circle closest to the ship if the playfield wraps
+        let center = self.position + playfield_delta(wrap_mode, self.position, center);
+        let hull = self.hitbox(hitbox_scale);
+
+        let mut inside = false;
+        for (i, &start) in hull.iter().enumerate() {
+            let end = hull[(i + 1) % hull.len()];
+            if distance_to_segment(center, start, end) < radius {
+                return true;
+            }
+            // even-odd rule, the outline isn't convex
+            if (start.y > center.y) != (end.y > center.y)
+                && center.x < start.x + (center.y - start.y) / (end.y - start.y) * (end.x - start.x)
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = if segment.length_squared() > 0.0 {
+        ((point - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(start + segment * t)
+}
+
+impl Default for Ship {
+    fn default() -> Self {
+        Self {
+            position: SIZE.mul(0.5),
+            velocity: Vec2::ZERO,
+            rotation: 0.0,
+            angular_velocity: 0.0,
+            status: ShipStatus::Alive,
+            last_shot: 0.0,
+            charge: 0.0,
+            shielded: false,
+            invulnerable_until: 0.0,
+            last_dash: f32::MIN,
+        }
+    }
+}
+
+/// A ship together with everything that belongs to whoever flies it
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Player {
+    pub(crate) ship: Ship,
+    pub(crate) render_thruster_plume: bool,
+    // the way the last thrust pushed the ship, the plume points the other way
+    #[serde(default)]
+    pub(crate) thrust_direction: Vec2,
+    // only used when the lives aren't shared, see `State::lifes_mut`
+    pub(crate) lifes: usize,
+    // when the current ship spawned
+    pub(crate) life_start: f32,
+    pub(crate) bombs: usize,
+}
+
+impl Player {
+    // every ship comes with this many, pickups add more up to `MAX_BOMBS`
+    pub(crate) const BOMBS_PER_LIFE: usize = 1;
+    pub(crate) const MAX_BOMBS: usize = 3;
+
+    pub(crate) fn new(index: usize, count: usize, lifes: usize, now: f32) -> Self {
+        let mut player = Self {
+            lifes,
+            ..Default::default()
+        };
+        player.respawn(index, count, now);
+        player
+    }
+
+    /// A fresh ship, side by side with the others around the center of the playfield
+    pub(crate) fn respawn(&mut self, index: usize, count: usize, now: f32) {
+        let offset = (index as f32 - (count - 1) as f32 * 0.5) * SCALE * 4.0;
+        self.ship = Ship {
+            position: SIZE.mul(0.5) + Vec2::new(offset, 0.0),
+            ..Default::default()
+        };
+        self.render_thruster_plume = false;
+        self.life_start = now;
+        self.bombs = self.bombs.max(Self::BOMBS_PER_LIFE);
+    }
+}
+
+pub(crate) const SHIP_POINTS: [Vec2; 5] = [
+    Vec2::new(-0.4, -0.5),
+    Vec2::new(0.0, 0.5),
+    Vec2::new(0.4, -0.5),
+    Vec2::new(0.3, -0.4),
+    Vec2::new(-0.3, -0.4),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle_beside_the_wing_misses() {
+        let ship = Ship::default();
+        // the nose points down, the wings are at y - 0.5 * SCALE
+        let beside = ship.position + Vec2::new(SCALE * 0.5, SCALE * 0.3);
+        assert!(!ship.overlaps_circle(beside, SCALE * 0.1, 1.0, WrapMode::Wrap));
+        // the old radius check would have counted this as a hit
+        assert!(ship.position.distance(beside) < SCALE * 0.7);
+    }
+
+    #[test]
+    fn circle_touching_the_nose_hits() {
+        let ship = Ship::default();
+        let nose = ship.position + Vec2::new(0.0, SCALE * 0.55);
+        assert!(ship.overlaps_circle(nose, SCALE * 0.1, 1.0, WrapMode::Wrap));
+    }
+
+    #[test]
+    fn turning_builds_up_the_same_at_any_frame_rate() {
+        let mut slow = Ship::default();
+        let mut fast = Ship::default();
+        slow.steer(10.0, 0.05);
+        fast.steer(10.0, 0.025);
+        fast.steer(10.0, 0.025);
+        assert!(slow.angular_velocity > 0.0 && slow.angular_velocity < 10.0);
+        assert!((slow.angular_velocity - fast.angular_velocity).abs() < 1e-4);
+
+        // never beyond the target
+        for _ in 0..100 {
+            slow.steer(10.0, 0.05);
+        }
+        assert_eq!(slow.angular_velocity, 10.0);
+    }
+
+    #[test]
+    fn a_smaller_hitbox_forgives_touching_the_nose() {
+        let ship = Ship::default();
+        let nose = ship.position + Vec2::new(0.0, SCALE * 0.55);
+        assert!(!ship.overlaps_circle(nose, SCALE * 0.1, 0.5, WrapMode::Wrap));
+    }
+
+    #[test]
+    fn circle_inside_the_hull_hits() {
+        let ship = Ship::default();
+        assert!(ship.overlaps_circle(ship.position, 0.1, 1.0, WrapMode::Wrap));
+    }
+
+    #[test]
+    fn collision_wraps_around_the_edges() {
+        let ship = Ship {
+            position: Vec2::new(1.0, SIZE.y * 0.5),
+            ..Default::default()
+        };
+        let across = Vec2::new(SIZE.x - 1.0, SIZE.y * 0.5);
+        assert!(ship.overlaps_circle(across, SCALE * 0.1, 1.0, WrapMode::Wrap));
+        // walls don't let anything reach across
+        assert!(!ship.overlaps_circle(across, SCALE * 0.1, 1.0, WrapMode::Bounce));
+    }
+}
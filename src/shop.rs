@@ -0,0 +1,150 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameState, State, MAX_LIFES};
+
+/// What the ore of the current run was spent on
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Upgrades {
+    // each level shortens the time between two shots
+    pub(crate) fire_rate: u32,
+}
+
+/// Seconds the shop ignores its keys after opening
+pub(crate) const SHOP_LOCKOUT: f32 = 0.5;
+
+impl Upgrades {
+    const MAX_FIRE_RATE: u32 = 3;
+
+    /// Applied to the cooldown between two shots
+    pub(crate) fn cooldown_factor(&self) -> f32 {
+        0.8_f32.powi(self.fire_rate as i32)
+    }
+}
+
+/// One line of the shop between waves
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum ShopItem {
+    FireRate,
+    ExtraLife,
+    Shield,
+    Leave,
+}
+
+impl ShopItem {
+    pub(crate) const ALL: [ShopItem; 4] = [
+        ShopItem::FireRate,
+        ShopItem::ExtraLife,
+        ShopItem::Shield,
+        ShopItem::Leave,
+    ];
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            ShopItem::FireRate => "FASTER FIRE",
+            ShopItem::ExtraLife => "EXTRA LIFE",
+            ShopItem::Shield => "SHIELD",
+            ShopItem::Leave => "NEXT WAVE",
+        }
+    }
+
+    /// Ore it costs, nothing once it can't be bought anymore
+    pub(crate) fn price(&self, state: &State) -> Option<usize> {
+        match self {
+            ShopItem::FireRate => (state.upgrades.fire_rate < Upgrades::MAX_FIRE_RATE)
+                .then(|| 8 * (state.upgrades.fire_rate as usize + 1)),
+            ShopItem::ExtraLife => {
+                let room = (0..state.players.len()).any(|index| state.lifes(index) < MAX_LIFES);
+                room.then_some(15)
+            }
+            ShopItem::Shield => state
+                .players
+                .iter()
+                .any(|player| !player.ship.shielded)
+                .then_some(6),
+            ShopItem::Leave => None,
+        }
+    }
+
+    /// Spends `price` ore on it, the whole crew gets what is bought
+    fn buy(&self, state: &mut State, price: usize) {
+        state.credits -= price;
+        match self {
+            ShopItem::FireRate => state.upgrades.fire_rate += 1,
+            ShopItem::ExtraLife => {
+                if state.shares_lifes() {
+                    state.lifes = (state.lifes + 1).min(MAX_LIFES);
+                } else {
+                    for player in state.players.iter_mut() {
+                        player.lifes = (player.lifes + 1).min(MAX_LIFES);
+                    }
+                }
+            }
+            ShopItem::Shield => {
+                for player in state.players.iter_mut() {
+                    player.ship.shielded = true;
+                }
+            }
+            ShopItem::Leave => {}
+        }
+    }
+}
+
+/// Up and down pick an upgrade, Enter buys it. Escape, Tab or buying the last line goes
+/// on to the next wave, so the shop never stands in the way. Space is left out as it
+/// also fires, and for a moment after opening no key counts at all.
+pub(crate) fn update_shop(state: &mut State) {
+    if state.shop_lockout > 0.0 {
+        state.shop_lockout = (state.shop_lockout - get_frame_time()).max(0.0);
+        return;
+    }
+    let count = ShopItem::ALL.len();
+    if is_key_pressed(KeyCode::Up) {
+        state.shop_index = (state.shop_index + count - 1) % count;
+    }
+    if is_key_pressed(KeyCode::Down) {
+        state.shop_index = (state.shop_index + 1) % count;
+    }
+
+    let item = ShopItem::ALL[state.shop_index];
+    let confirm = is_key_pressed(KeyCode::Enter);
+    if confirm && item != ShopItem::Leave {
+        state.shop_message = match item.price(state) {
+            None => "SOLD OUT",
+            Some(price) if state.credits < price => "NOT ENOUGH ORE",
+            Some(price) => {
+                item.buy(state, price);
+                "BOUGHT"
+            }
+        };
+    }
+
+    if (confirm && item == ShopItem::Leave)
+        || is_key_pressed(KeyCode::Escape)
+        || is_key_pressed(KeyCode::Tab)
+    {
+        state.game_state = GameState::Playing;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, game::reset_game, settings::Settings, sound::silence};
+
+    #[test]
+    fn upgrades_get_pricier_until_sold_out() {
+        let mut state = State::new(silence(), Settings::default(), Config::default(), Some(1));
+        reset_game(&mut state);
+        state.credits = 100;
+
+        let mut spent = vec![];
+        while let Some(price) = ShopItem::FireRate.price(&state) {
+            ShopItem::FireRate.buy(&mut state, price);
+            spent.push(price);
+        }
+        assert_eq!(spent, vec![8, 16, 24]);
+        assert_eq!(state.credits, 52);
+        assert!(state.upgrades.cooldown_factor() < 0.6);
+    }
+}
@@ -0,0 +1,336 @@
+#[cfg(test)]
+use std::rc::Rc;
+use std::{cell::RefCell, collections::HashMap, time::Instant};
+
+use macroquad::{
+    audio::{load_sound, play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound},
+    math::Vec2,
+    prelude::warn,
+};
+
+use crate::{config::WrapMode, game::playfield_delta, SIZE};
+
+pub(crate) struct Sounds {
+    pub(crate) blop_low: Sound,
+    pub(crate) blop_high: Sound,
+    pub(crate) thruster: Sound,
+    pub(crate) explosion: Sound,
+    pub(crate) shoot: Sound,
+    pub(crate) asteroid: Sound,
+    pub(crate) extra_life: Sound,
+    pub(crate) shield: Sound,
+    pub(crate) dash: Sound,
+    pub(crate) chain: Sound,
+    // newer sounds may be missing from an older assets folder, the game plays on without
+    pub(crate) alien_drone: Option<Sound>,
+    pub(crate) bomb: Option<Sound>,
+    pub(crate) menu_music: Option<Sound>,
+    pub(crate) game_music: Option<Sound>,
+}
+
+/// Every sound of the game. The simulation only names them, playing is up to an `AudioSink`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum SoundEffect {
+    BlopLow,
+    BlopHigh,
+    Thruster,
+    Explosion,
+    Shoot,
+    Asteroid,
+    ExtraLife,
+    Shield,
+    Dash,
+    Chain,
+    // a deeper blast than a ship exploding
+    Bomb,
+    // loops while an alien is around
+    AlienDrone,
+    // the calm track of the menu and the one played over a run, both always looping
+    MenuMusic,
+    GameMusic,
+}
+
+impl SoundEffect {
+    /// Seconds before the same sound may start again, the ones that pile up when a lot
+    /// breaks at once. Everything else plays every time.
+    fn min_interval(&self) -> f32 {
+        match self {
+            SoundEffect::Asteroid => 0.03,
+            SoundEffect::Explosion => 0.05,
+            SoundEffect::Shoot => 0.02,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Where the simulation sends its sounds, so it can also run without an audio device
+pub(crate) trait AudioSink {
+    fn play(&self, sound: SoundEffect, volume: f32);
+    /// Plays over and over until stopped
+    fn play_looped(&self, sound: SoundEffect, volume: f32);
+    fn stop(&self, sound: SoundEffect);
+    /// Changes the volume of a sound that is already playing
+    fn set_volume(&self, sound: SoundEffect, volume: f32);
+}
+
+impl Sounds {
+    /// Whether either music track is there, they don't come with every assets folder
+    pub(crate) fn has_music(&self) -> bool {
+        self.menu_music.is_some() || self.game_music.is_some()
+    }
+
+    fn get(&self, sound: SoundEffect) -> Option<&Sound> {
+        let sound = match sound {
+            SoundEffect::BlopLow => &self.blop_low,
+            SoundEffect::BlopHigh => &self.blop_high,
+            SoundEffect::Thruster => &self.thruster,
+            SoundEffect::Explosion => &self.explosion,
+            SoundEffect::Shoot => &self.shoot,
+            SoundEffect::Asteroid => &self.asteroid,
+            SoundEffect::ExtraLife => &self.extra_life,
+            SoundEffect::Shield => &self.shield,
+            SoundEffect::Dash => &self.dash,
+            SoundEffect::Chain => &self.chain,
+            SoundEffect::AlienDrone => return self.alien_drone.as_ref(),
+            SoundEffect::Bomb => self.bomb.as_ref().unwrap_or(&self.explosion),
+            SoundEffect::MenuMusic => return self.menu_music.as_ref(),
+            SoundEffect::GameMusic => return self.game_music.as_ref(),
+        };
+        Some(sound)
+    }
+}
+
+impl AudioSink for Sounds {
+    fn play(&self, sound: SoundEffect, volume: f32) {
+        if let Some(sound) = self.get(sound) {
+            play_sound_with_volume(sound, volume);
+        }
+    }
+
+    fn play_looped(&self, sound: SoundEffect, volume: f32) {
+        if let Some(sound) = self.get(sound) {
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    looped: true,
+                    volume,
+                },
+            );
+        }
+    }
+
+    fn stop(&self, sound: SoundEffect) {
+        if let Some(sound) = self.get(sound) {
+            stop_sound(sound);
+        }
+    }
+
+    fn set_volume(&self, sound: SoundEffect, volume: f32) {
+        if let Some(sound) = self.get(sound) {
+            set_sound_volume(sound, volume);
+        }
+    }
+}
+
+/// Passes sounds on to another sink but drops a sound that just started, so a cascade of
+/// breaking rocks doesn't stack dozens of copies into a clipping mix. Each sound is limited
+/// on its own, a burst of rocks never silences an explosion.
+pub(crate) struct Limiter<A> {
+    inner: A,
+    last_played: RefCell<HashMap<SoundEffect, Instant>>,
+}
+
+impl<A: AudioSink> Limiter<A> {
+    pub(crate) fn new(inner: A) -> Self {
+        Self {
+            inner,
+            last_played: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<A: AudioSink> AudioSink for Limiter<A> {
+    fn play(&self, sound: SoundEffect, volume: f32) {
+        let now = Instant::now();
+        let mut last_played = self.last_played.borrow_mut();
+        if let Some(last) = last_played.get(&sound) {
+            if now.duration_since(*last).as_secs_f32() < sound.min_interval() {
+                return;
+            }
+        }
+        last_played.insert(sound, now);
+        self.inner.play(sound, volume);
+    }
+
+    fn play_looped(&self, sound: SoundEffect, volume: f32) {
+        self.inner.play_looped(sound, volume);
+    }
+
+    fn stop(&self, sound: SoundEffect) {
+        self.inner.stop(sound);
+    }
+
+    fn set_volume(&self, sound: SoundEffect, volume: f32) {
+        self.inner.set_volume(sound, volume);
+    }
+}
+
+/// Plays nothing, stands in until a real sink is put in place
+pub(crate) struct Silence;
+
+impl AudioSink for Silence {
+    fn play(&self, _sound: SoundEffect, _volume: f32) {}
+
+    fn play_looped(&self, _sound: SoundEffect, _volume: f32) {}
+
+    fn stop(&self, _sound: SoundEffect) {}
+
+    fn set_volume(&self, _sound: SoundEffect, _volume: f32) {}
+}
+
+pub(crate) fn silence() -> Box<dyn AudioSink> {
+    Box::new(Silence)
+}
+
+/// Remembers what would have been played instead of playing it. Clones share the log,
+/// so a test can keep one while the `State` owns another.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SoundLog {
+    pub(crate) played: Rc<RefCell<Vec<SoundEffect>>>,
+    pub(crate) stopped: Rc<RefCell<Vec<SoundEffect>>>,
+}
+
+#[cfg(test)]
+impl AudioSink for SoundLog {
+    fn play(&self, sound: SoundEffect, _volume: f32) {
+        self.played.borrow_mut().push(sound);
+    }
+
+    fn play_looped(&self, sound: SoundEffect, _volume: f32) {
+        self.played.borrow_mut().push(sound);
+    }
+
+    fn stop(&self, sound: SoundEffect) {
+        self.stopped.borrow_mut().push(sound);
+    }
+
+    fn set_volume(&self, _sound: SoundEffect, _volume: f32) {}
+}
+
+fn play_sound_with_volume(sound: &Sound, volume: f32) {
+    play_sound(
+        sound,
+        PlaySoundParams {
+            looped: false,
+            volume,
+        },
+    );
+}
+
+/// Volume of a sound at `position` heard from `listener`, quieter the further away it is.
+/// Macroquad's `PlaySoundParams` has no stereo pan, so distance is all we can convey.
+pub(crate) fn positional_volume(
+    volume: f32,
+    position: Vec2,
+    listener: Vec2,
+    wrap_mode: WrapMode,
+) -> f32 {
+    // even sounds from the far side of the playfield stay audible
+    const MIN_FACTOR: f32 = 0.4;
+    let max_distance = (SIZE * 0.5).length();
+    let distance = playfield_delta(wrap_mode, listener, position).length() / max_distance;
+    volume * (1.0 - (1.0 - MIN_FACTOR) * distance.min(1.0))
+}
+
+pub(crate) async fn load_sounds() -> Sounds {
+    let blop_lo = load_sound("./assets/bloop_lo.wav")
+        .await
+        .expect("Sound bloop_lo not found!");
+    let blop_high = load_sound("./assets/bloop_hi.wav")
+        .await
+        .expect("Sound bloop_hi not found!");
+    let thruster = load_sound("./assets/thrust.wav")
+        .await
+        .expect("Sound thruster not found!");
+    let explosion = load_sound("./assets/explode.wav")
+        .await
+        .expect("Sound explosion not found!");
+    let shoot = load_sound("./assets/shoot.wav")
+        .await
+        .expect("Sound shoot not found!");
+    let asteroid = load_sound("./assets/asteroid.wav")
+        .await
+        .expect("Sound asteroid not found!");
+    let extra_life = load_sound("./assets/extra_life.wav")
+        .await
+        .expect("Sound extra_life not found!");
+    let shield = load_sound("./assets/shield.wav")
+        .await
+        .expect("Sound shield not found!");
+    let dash = load_sound("./assets/dash.wav")
+        .await
+        .expect("Sound dash not found!");
+    let chain = load_sound("./assets/chain.wav")
+        .await
+        .expect("Sound chain not found!");
+    let alien_drone = load_optional_sound("./assets/alien_drone.wav").await;
+    let bomb = load_optional_sound("./assets/bomb.wav").await;
+    let menu_music = load_optional_sound("./assets/music_menu.wav").await;
+    let game_music = load_optional_sound("./assets/music_game.wav").await;
+
+    Sounds {
+        blop_low: blop_lo,
+        blop_high,
+        thruster,
+        explosion,
+        shoot,
+        asteroid,
+        extra_life,
+        shield,
+        dash,
+        chain,
+        alien_drone,
+        bomb,
+        menu_music,
+        game_music,
+    }
+}
+
+/// A sound the game can do without, a missing one is only worth a warning
+async fn load_optional_sound(path: &str) -> Option<Sound> {
+    match load_sound(path).await {
+        Ok(sound) => Some(sound),
+        Err(err) => {
+            warn!("Playing without {}: {}", path, err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_burst_of_one_sound_plays_once_without_muting_others() {
+        let log = SoundLog::default();
+        let limiter = Limiter::new(log.clone());
+        for _ in 0..5 {
+            limiter.play(SoundEffect::Asteroid, 1.0);
+        }
+        limiter.play(SoundEffect::Explosion, 1.0);
+        limiter.play(SoundEffect::BlopLow, 1.0);
+        limiter.play(SoundEffect::BlopLow, 1.0);
+
+        assert_eq!(
+            *log.played.borrow(),
+            vec![
+                SoundEffect::Asteroid,
+                SoundEffect::Explosion,
+                SoundEffect::BlopLow,
+                SoundEffect::BlopLow
+            ]
+        );
+    }
+}
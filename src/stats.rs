@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{alien::AlienSize, rock::RockSize};
+
+/// Totals of the current run, shown on the game over screen
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct RunStats {
+    // broken by the players' shots and bombs, indexed like `RockSize`
+    pub(crate) rocks_destroyed: [u32; 4],
+    // broken by anything else: aliens, the gravity well or a ship crashing into them
+    pub(crate) rocks_lost: u32,
+    // shot down by the players, indexed like `AlienSize`
+    pub(crate) aliens_destroyed: [u32; 2],
+    pub(crate) shots_fired: u32,
+    // shots that struck at least one rock or alien
+    pub(crate) shots_hit: u32,
+    // seconds since a ship was last lost and the longest such stretch
+    pub(crate) streak: f32,
+    pub(crate) longest_streak: f32,
+    // waves cleared before a single shot was fired in them
+    pub(crate) waves_without_firing: u32,
+}
+
+impl RunStats {
+    pub(crate) fn rock_destroyed(&mut self, size: &RockSize) {
+        let index = match size {
+            RockSize::Boss => 0,
+            RockSize::Big => 1,
+            RockSize::Medium => 2,
+            RockSize::Small => 3,
+        };
+        self.rocks_destroyed[index] += 1;
+    }
+
+    pub(crate) fn total_rocks_destroyed(&self) -> u32 {
+        self.rocks_destroyed.iter().sum()
+    }
+
+    pub(crate) fn alien_destroyed(&mut self, size: &AlienSize) {
+        let index = match size {
+            AlienSize::Big => 0,
+            AlienSize::Small => 1,
+        };
+        self.aliens_destroyed[index] += 1;
+    }
+
+    /// Share of the shots that struck something, nothing before the first shot
+    pub(crate) fn accuracy(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.shots_hit as f32 / self.shots_fired as f32)
+    }
+
+    /// Counts up while every ship is flying, a lost one starts the streak over
+    pub(crate) fn survive(&mut self, all_alive: bool, delta: f32) {
+        if all_alive {
+            self.streak += delta;
+            self.longest_streak = self.longest_streak.max(self.streak);
+        } else {
+            self.streak = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_lost_ship_starts_the_streak_over_but_keeps_the_longest() {
+        let mut stats = RunStats::default();
+        stats.survive(true, 5.0);
+        stats.survive(false, 1.0);
+        stats.survive(true, 2.0);
+        assert_eq!(stats.streak, 2.0);
+        assert_eq!(stats.longest_streak, 5.0);
+    }
+
+    #[test]
+    fn accuracy_is_the_share_of_shots_that_struck() {
+        let mut stats = RunStats::default();
+        assert_eq!(stats.accuracy(), None);
+        stats.shots_fired = 4;
+        stats.shots_hit = 1;
+        assert_eq!(stats.accuracy(), Some(0.25));
+    }
+}
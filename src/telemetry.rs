@@ -0,0 +1,156 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+};
+
+use macroquad::prelude::*;
+
+use crate::{alien::AlienSize, rock::RockSize};
+
+const HEADER: &str = "time,wave,event,size,score";
+
+/// Something that happened in a run, one line of the log each
+pub(crate) enum RunEvent<'a> {
+    WaveStarted { rocks: usize },
+    RockDestroyed { size: &'a RockSize, points: usize },
+    LifeLost,
+    AlienSpawned { size: &'a AlienSize },
+    AlienDestroyed { size: &'a AlienSize },
+    RunEnded { score: usize },
+}
+
+impl RunEvent<'_> {
+    fn name(&self) -> &'static str {
+        match self {
+            RunEvent::WaveStarted { .. } => "wave_started",
+            RunEvent::RockDestroyed { .. } => "rock_destroyed",
+            RunEvent::LifeLost => "life_lost",
+            RunEvent::AlienSpawned { .. } => "alien_spawned",
+            RunEvent::AlienDestroyed { .. } => "alien_destroyed",
+            RunEvent::RunEnded { .. } => "run_ended",
+        }
+    }
+
+    /// The size and score columns, empty where they don't apply. A new wave puts
+    /// its number of rocks in the score column.
+    fn columns(&self) -> (&'static str, String) {
+        match self {
+            RunEvent::WaveStarted { rocks } => ("", rocks.to_string()),
+            RunEvent::RockDestroyed { size, points } => (rock_size_name(size), points.to_string()),
+            RunEvent::LifeLost => ("", String::new()),
+            RunEvent::AlienSpawned { size } | RunEvent::AlienDestroyed { size } => {
+                (alien_size_name(size), String::new())
+            }
+            RunEvent::RunEnded { score } => ("", score.to_string()),
+        }
+    }
+}
+
+fn rock_size_name(size: &RockSize) -> &'static str {
+    match size {
+        RockSize::Boss => "boss",
+        RockSize::Big => "big",
+        RockSize::Medium => "medium",
+        RockSize::Small => "small",
+    }
+}
+
+fn alien_size_name(size: &AlienSize) -> &'static str {
+    match size {
+        AlienSize::Big => "big",
+        AlienSize::Small => "small",
+    }
+}
+
+/// The events of every run appended to a CSV file, for `--log <path>` or the `run_log`
+/// config. Off unless opened, then every call to `log` is a no-op. Lines are buffered
+/// and only written out once the buffer is full or a run ends, so logging never
+/// waits on the disk in the middle of a frame.
+#[derive(Default)]
+pub(crate) struct RunLog {
+    writer: Option<BufWriter<File>>,
+    // the demo plays real runs, none of which should end up in the log
+    pub(crate) muted: bool,
+}
+
+impl RunLog {
+    pub(crate) fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let new = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if new {
+            writeln!(writer, "{}", HEADER)?;
+        }
+        Ok(Self {
+            writer: Some(writer),
+            muted: false,
+        })
+    }
+
+    /// Adds `event` at `now` seconds into the game during `wave`. The end of a run
+    /// writes out everything buffered so far.
+    pub(crate) fn log(&mut self, now: f32, wave: usize, event: RunEvent) {
+        if self.muted {
+            return;
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let (size, score) = event.columns();
+        let line = format!("{:.3},{},{},{},{}", now, wave, event.name(), size, score);
+        if let Err(err) = writeln!(writer, "{}", line) {
+            warn!("Unable to write to the run log, stopping it: {}", err);
+            self.writer = None;
+            return;
+        }
+        if matches!(event, RunEvent::RunEnded { .. }) {
+            self.flush();
+        }
+    }
+
+    pub(crate) fn flush(&mut self) {
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(err) = writer.flush() {
+                warn!("Unable to write to the run log: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_a_line_per_event_below_a_single_header() {
+        let path = std::env::temp_dir().join("big_space_rock_run_log.csv");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        for _ in 0..2 {
+            let mut log = RunLog::open(path).unwrap();
+            log.log(0.5, 1, RunEvent::WaveStarted { rocks: 20 });
+            log.log(
+                1.25,
+                1,
+                RunEvent::RockDestroyed {
+                    size: &RockSize::Big,
+                    points: 40,
+                },
+            );
+            log.muted = true;
+            log.log(2.0, 1, RunEvent::LifeLost);
+            log.muted = false;
+            log.log(3.0, 2, RunEvent::RunEnded { score: 40 });
+        }
+
+        let content = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(lines[1], "0.500,1,wave_started,,20");
+        assert_eq!(lines[2], "1.250,1,rock_destroyed,big,40");
+        assert_eq!(lines[3], "3.000,2,run_ended,,40");
+        let _ = std::fs::remove_file(path);
+    }
+}
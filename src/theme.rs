@@ -0,0 +1,138 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Colors everything on screen is drawn with
+pub(crate) struct Theme {
+    pub(crate) background: Color,
+    // the ship, plain rocks, particles and the HUD
+    pub(crate) line: Color,
+    pub(crate) armored: Color,
+    pub(crate) alien: Color,
+    pub(crate) power_up: Color,
+    pub(crate) projectile: Color,
+    // incoming fire, the same as `projectile` leaves telling them apart to the shapes
+    pub(crate) alien_projectile: Color,
+    // tints the playfield during the last stand
+    pub(crate) danger: Color,
+    // plain rocks step through these as the waves go by, none keeps them in `line`
+    pub(crate) rock_tiers: &'static [Color],
+}
+
+impl Theme {
+    // waves spent on each tier
+    const WAVES_PER_TIER: usize = 2;
+
+    /// Color of the plain rocks during `wave`, the last tier stays once it's reached
+    pub(crate) fn rock_color(&self, wave: usize) -> Color {
+        let tier = wave.saturating_sub(1) / Self::WAVES_PER_TIER;
+        match self.rock_tiers.last() {
+            Some(last) => *self.rock_tiers.get(tier).unwrap_or(last),
+            None => self.line,
+        }
+    }
+}
+
+const CLASSIC: Theme = Theme {
+    background: BLACK,
+    line: WHITE,
+    armored: WHITE,
+    alien: WHITE,
+    power_up: WHITE,
+    projectile: WHITE,
+    alien_projectile: WHITE,
+    danger: Color::new(0.8, 0.0, 0.0, 1.0),
+    rock_tiers: &[],
+};
+
+// the classic look with rocks going from white over cool to warm hues the deeper the run
+const TINTED: Theme = Theme {
+    rock_tiers: &[
+        WHITE,
+        Color::new(0.7, 0.9, 1.0, 1.0),
+        Color::new(0.5, 0.7, 1.0, 1.0),
+        Color::new(0.75, 0.55, 1.0, 1.0),
+        Color::new(1.0, 0.9, 0.45, 1.0),
+        Color::new(1.0, 0.65, 0.3, 1.0),
+        Color::new(1.0, 0.4, 0.3, 1.0),
+    ],
+    ..CLASSIC
+};
+
+const HIGH_CONTRAST: Theme = Theme {
+    background: BLACK,
+    line: WHITE,
+    armored: Color::new(1.0, 1.0, 0.0, 1.0),
+    alien: Color::new(1.0, 0.0, 1.0, 1.0),
+    power_up: Color::new(0.0, 1.0, 1.0, 1.0),
+    projectile: Color::new(1.0, 1.0, 0.0, 1.0),
+    alien_projectile: Color::new(1.0, 0.0, 1.0, 1.0),
+    danger: Color::new(1.0, 0.0, 0.0, 1.0),
+    rock_tiers: &[],
+};
+
+// Okabe-Ito colors, told apart with any of the common color vision deficiencies
+const COLORBLIND: Theme = Theme {
+    background: Color::new(0.05, 0.05, 0.08, 1.0),
+    line: Color::new(0.95, 0.95, 0.95, 1.0),
+    armored: Color::new(0.9, 0.62, 0.0, 1.0),
+    alien: Color::new(0.84, 0.37, 0.0, 1.0),
+    power_up: Color::new(0.34, 0.71, 0.91, 1.0),
+    projectile: Color::new(0.94, 0.89, 0.26, 1.0),
+    alien_projectile: Color::new(0.84, 0.37, 0.0, 1.0),
+    danger: Color::new(0.84, 0.37, 0.0, 1.0),
+    rock_tiers: &[],
+};
+
+/// The selectable themes, cycled with T and stored in the settings
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ThemeKind {
+    #[default]
+    Classic,
+    HighContrast,
+    Colorblind,
+    Tinted,
+}
+
+impl ThemeKind {
+    pub(crate) fn colors(&self) -> &'static Theme {
+        match self {
+            ThemeKind::Classic => &CLASSIC,
+            ThemeKind::HighContrast => &HIGH_CONTRAST,
+            ThemeKind::Colorblind => &COLORBLIND,
+            ThemeKind::Tinted => &TINTED,
+        }
+    }
+
+    pub(crate) fn next(&self) -> Self {
+        match self {
+            ThemeKind::Classic => ThemeKind::HighContrast,
+            ThemeKind::HighContrast => ThemeKind::Colorblind,
+            ThemeKind::Colorblind => ThemeKind::Tinted,
+            ThemeKind::Tinted => ThemeKind::Classic,
+        }
+    }
+
+    pub(crate) fn previous(&self) -> Self {
+        match self {
+            ThemeKind::Classic => ThemeKind::Tinted,
+            ThemeKind::HighContrast => ThemeKind::Classic,
+            ThemeKind::Colorblind => ThemeKind::HighContrast,
+            ThemeKind::Tinted => ThemeKind::Colorblind,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ThemeKind::Classic => "CLASSIC",
+            ThemeKind::HighContrast => "HIGH CONTRAST",
+            ThemeKind::Colorblind => "COLORBLIND",
+            ThemeKind::Tinted => "TINTED",
+        }
+    }
+}
+
+/// `color` with its alpha replaced
+pub(crate) fn with_alpha(color: Color, alpha: f32) -> Color {
+    Color::new(color.r, color.g, color.b, alpha)
+}